@@ -0,0 +1,23 @@
+//! Feeds arbitrary input to the scanner, which must turn anything into tokens without panicking.
+//!
+//! Needs a nightly toolchain and `cargo install cargo-fuzz`, then from the repository root:
+//!
+//! ```text
+//! cargo +nightly fuzz run scanner
+//! ```
+//!
+//! Crashing inputs are saved under `fuzz/artifacts/scanner/` and can be replayed by passing the
+//! file after the target name.
+
+#![no_main]
+
+use interpret::scanner::TokenType;
+use interpret::Scanner;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data);
+    let tokens = Scanner::new(&source).scan_tokens();
+
+    assert_eq!(tokens.last().map(|token| &token.kind), Some(&TokenType::Eof));
+});
@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use interpret::Scanner;
+
+/// Builds a Lox program of about `lines` lines mixing the kinds of tokens the scanner handles
+fn synthetic_source(lines: usize) -> String {
+    let mut source = String::new();
+
+    for i in 0..lines {
+        match i % 5 {
+            0 => source.push_str(&format!("var value_{} = {} * 3.25 + 1_000;\n", i, i)),
+            1 => source.push_str(&format!("print \"line {} says \\\"hi\\\"\";\n", i)),
+            2 => source.push_str("// a comment that runs until the end of the line\n"),
+            3 => source.push_str(&format!(
+                "fun f{}(a, b) {{ if (a >= b and !nil) return a; else return b % 2; }}\n",
+                i
+            )),
+            _ => source.push_str("/* block /* nested */ comment */ while (x < 10) x = x + 1;\n"),
+        }
+    }
+    source
+}
+
+fn scan_tokens(c: &mut Criterion) {
+    let source = synthetic_source(10_000);
+
+    let mut group = c.benchmark_group("scanner");
+    // Reported as bytes of source per second
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.bench_function("scan_tokens", |b| {
+        b.iter(|| Scanner::new(black_box(&source)).scan_tokens())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scan_tokens);
+criterion_main!(benches);
@@ -0,0 +1,295 @@
+use std::borrow::Cow;
+
+use crate::expr::{Expr, LiteralValue};
+use crate::scanner;
+use crate::stmt::Stmt;
+
+/// Renders an expression in an unambiguous Lisp-like prefix form, e.g. `1 + 2 * 3` becomes
+/// `(+ 1 (* 2 3))`
+pub fn print(expr: &Expr) -> String {
+    match expr {
+        Expr::Literal(literal) => match literal {
+            LiteralValue::Number(value) => value.to_string(),
+            LiteralValue::String(value) => scanner::escape(value),
+            LiteralValue::Bool(value) => value.to_string(),
+            LiteralValue::Nil => "nil".to_string(),
+        },
+        Expr::Grouping(inner) => parenthesize("group", &[inner]),
+        Expr::Unary { op, right } => parenthesize(&op.text(), &[right]),
+        Expr::Binary { left, op, right } | Expr::Logical { left, op, right } => {
+            parenthesize(&op.text(), &[left, right])
+        }
+        Expr::Variable { name, .. } => name.text().into_owned(),
+        Expr::This { .. } => "this".to_string(),
+        Expr::Super { method, .. } => format!("(. super {})", method.text()),
+        Expr::Call { callee, args, .. } => {
+            let exprs: Vec<&Expr> = std::iter::once(&**callee).chain(args).collect();
+            parenthesize("call", &exprs)
+        }
+        Expr::Get { object, name } => format!("(. {} {})", print(object), name.text()),
+        Expr::Set {
+            object,
+            name,
+            value,
+        } => format!("(= (. {} {}) {})", print(object), name.text(), print(value)),
+        Expr::Function { params, body, .. } => {
+            let params: Vec<Cow<str>> = params.iter().map(|param| param.text()).collect();
+            let mut output = format!("(fun ({})", params.join(" "));
+            push_statements(&mut output, body);
+            output
+        }
+        Expr::Ternary {
+            cond,
+            then_expr,
+            else_expr,
+        } => parenthesize("?:", &[cond, then_expr, else_expr]),
+        Expr::List(elements) => {
+            let elements: Vec<&Expr> = elements.iter().collect();
+            parenthesize("list", &elements)
+        }
+        Expr::Index { object, index, .. } => parenthesize("[]", &[object, index]),
+        Expr::SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } => format!(
+            "(= ([] {} {}) {})",
+            print(object),
+            print(index),
+            print(value)
+        ),
+        Expr::Assign { name, value, .. } => parenthesize(&format!("= {}", name.text()), &[value]),
+    }
+}
+
+/// Renders a statement in the same form, e.g. `print 1 + 2;` becomes `(print (+ 1 2))`
+pub fn print_stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Expression(expr) => format!("(; {})", print(expr)),
+        Stmt::Print(expr) => format!("(print {})", print(expr)),
+        Stmt::Var { name, initializer } => match initializer {
+            Some(initializer) => format!("(var {} = {})", name.text(), print(initializer)),
+            None => format!("(var {})", name.text()),
+        },
+        Stmt::Class {
+            name,
+            superclass,
+            methods,
+        } => {
+            let mut output = format!("(class {}", name.text());
+            if let Some(superclass) = superclass {
+                output.push_str(&format!(" < {}", print(superclass)));
+            }
+            push_statements(&mut output, methods);
+            output
+        }
+        Stmt::Function { name, params, body } => {
+            let params: Vec<Cow<str>> = params.iter().map(|param| param.text()).collect();
+            let mut output = format!("(fun {}({})", name.text(), params.join(" "));
+            push_statements(&mut output, body);
+            output
+        }
+        Stmt::Return { value, .. } => match value {
+            Some(value) => format!("(return {})", print(value)),
+            None => "(return)".to_string(),
+        },
+        Stmt::Block(statements) => {
+            let mut output = "(block".to_string();
+            push_statements(&mut output, statements);
+            output
+        }
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => match else_branch {
+            Some(else_branch) => format!(
+                "(if-else {} {} {})",
+                print(condition),
+                print_stmt(then_branch),
+                print_stmt(else_branch)
+            ),
+            None => format!("(if {} {})", print(condition), print_stmt(then_branch)),
+        },
+        Stmt::While {
+            condition,
+            body,
+            increment: None,
+        } => format!("(while {} {})", print(condition), print_stmt(body)),
+        Stmt::While {
+            condition,
+            body,
+            increment: Some(increment),
+        } => format!(
+            "(while {} {} {})",
+            print(condition),
+            print_stmt(body),
+            print(increment)
+        ),
+        Stmt::Break { .. } => "(break)".to_string(),
+        Stmt::Continue { .. } => "(continue)".to_string(),
+    }
+}
+
+/// Appends each statement and closes the list
+fn push_statements(output: &mut String, statements: &[Stmt]) {
+    for statement in statements {
+        output.push(' ');
+        output.push_str(&print_stmt(statement));
+    }
+    output.push(')');
+}
+
+fn parenthesize(name: &str, exprs: &[&Expr]) -> String {
+    let mut output = format!("({}", name);
+
+    for expr in exprs {
+        output.push(' ');
+        output.push_str(&print(expr));
+    }
+
+    output.push(')');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast_printer::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn print_source(source: &str) -> String {
+        let expr = Parser::new(Scanner::new(source).scan_tokens())
+            .parse_expression()
+            .unwrap();
+
+        print(&expr)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(print_source("1 + 2 * 3"), "(+ 1 (* 2 3))");
+    }
+
+    #[test]
+    fn modulo_precedence() {
+        assert_eq!(print_source("1 + 7 % 4 * 2"), "(+ 1 (* (% 7 4) 2))");
+    }
+
+    #[test]
+    fn anonymous_function() {
+        assert_eq!(
+            print_source("f(fun (a) { return a; })"),
+            "(call f (fun (a) (return a)))"
+        );
+    }
+
+    #[test]
+    fn ternary() {
+        assert_eq!(
+            print_source("a = b or c ? 1 : d ? 2 : 3"),
+            "(= a (?: (or b c) 1 (?: d 2 3)))"
+        );
+    }
+
+    #[test]
+    fn grouping() {
+        assert_eq!(print_source("(1 + 2) * 3"), "(* (group (+ 1 2)) 3)");
+    }
+
+    #[test]
+    fn unary_and_literals() {
+        assert_eq!(
+            print_source("-4.5 == !true != nil < \"a\""),
+            "(!= (== (- 4.5) (! true)) (< nil \"a\"))"
+        );
+    }
+
+    #[test]
+    fn escaped_string() {
+        assert_eq!(print_source(r#""a\"b\n""#), r#""a\"b\n""#);
+    }
+
+    #[test]
+    fn assignment() {
+        assert_eq!(print_source("a = b = c + 1"), "(= a (= b (+ c 1)))");
+    }
+
+    #[test]
+    fn logical() {
+        assert_eq!(print_source("a or b and c"), "(or a (and b c))");
+        assert_eq!(print_source("a == b and c"), "(and (== a b) c)");
+    }
+
+    #[test]
+    fn call() {
+        assert_eq!(print_source("f(1, g())(2)"), "(call (call f 1 (call g)) 2)");
+    }
+
+    #[test]
+    fn get() {
+        assert_eq!(print_source("a.b().c"), "(. (call (. a b)) c)");
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(print_source("[1, [2]][0]"), "([] (list 1 (list 2)) 0)");
+        assert_eq!(print_source("a[i] = []"), "(= ([] a i) (list))");
+    }
+
+    #[test]
+    fn set() {
+        assert_eq!(print_source("a.b = c.d = 1"), "(= (. a b) (= (. c d) 1))");
+    }
+
+    fn print_program(source: &str) -> Vec<String> {
+        Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap()
+            .iter()
+            .map(print_stmt)
+            .collect()
+    }
+
+    #[test]
+    fn simple_statements() {
+        assert_eq!(
+            print_program("print 1 + 2; var a; var b = a; a;"),
+            ["(print (+ 1 2))", "(var a)", "(var b = a)", "(; a)"]
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(
+            print_program("if (a) { print 1; } else print 2; while (b) b = false;"),
+            [
+                "(if-else a (block (print 1)) (print 2))",
+                "(while b (; (= b false)))"
+            ]
+        );
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(
+            print_program("for (var i = 0; i < 3; i = i + 1) { continue; } while (true) break;"),
+            [
+                "(block (var i = 0) (while (< i 3) (block (continue)) (= i (+ i 1))))",
+                "(while true (break))"
+            ]
+        );
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(
+            print_program("fun f(a, b) { return a; } class B < A { m() { return; } }"),
+            [
+                "(fun f(a b) (return a))",
+                "(class B < A (fun m() (return)))"
+            ]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::interpreter::{is_truthy, Interpreter, RuntimeError};
+use crate::value::Value;
+
+/// Anything that can be called from Lox code, the `Display` impl is how it gets printed
+pub trait LoxCallable: fmt::Display {
+    /// Number of required arguments
+    fn arity(&self) -> usize;
+
+    /// Most arguments accepted, more than `arity()` when trailing ones can be left out
+    fn max_arity(&self) -> usize {
+        self.arity()
+    }
+
+    /// `args` always has `arity()` to `max_arity()` values, the caller checks it
+    fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError>;
+}
+
+/// Built-in function implemented in Rust
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    /// Arguments accepted on top of `arity`
+    pub optional: usize,
+    /// Errors are reported at the line of the call
+    pub function: fn(&mut Interpreter, &[Value]) -> Result<Value, String>,
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn max_arity(&self) -> usize {
+        self.arity + self.optional
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        (self.function)(interp, &args).map_err(|message| interp.error_at_call(message))
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<native fn>")
+    }
+}
+
+/// Built-ins defined in the global scope of every interpreter
+pub fn natives() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction {
+            name: "clock",
+            arity: 0,
+            optional: 0,
+            function: clock,
+        },
+        NativeFunction {
+            name: "len",
+            arity: 1,
+            optional: 0,
+            function: len,
+        },
+        NativeFunction {
+            name: "substring",
+            arity: 3,
+            optional: 0,
+            function: substring,
+        },
+        NativeFunction {
+            name: "upper",
+            arity: 1,
+            optional: 0,
+            function: upper,
+        },
+        NativeFunction {
+            name: "lower",
+            arity: 1,
+            optional: 0,
+            function: lower,
+        },
+        NativeFunction {
+            name: "input",
+            arity: 0,
+            optional: 0,
+            function: input,
+        },
+        NativeFunction {
+            name: "assert",
+            arity: 1,
+            optional: 1,
+            function: assert,
+        },
+        NativeFunction {
+            name: "type",
+            arity: 1,
+            optional: 0,
+            function: type_of,
+        },
+    ]
+}
+
+/// Seconds since the Unix epoch
+fn clock(_interp: &mut Interpreter, _args: &[Value]) -> Result<Value, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| err.to_string())?;
+    Ok(Value::Number(now.as_secs_f64()))
+}
+
+/// Number of characters in a string or elements in a list
+fn len(_interp: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Str(value) => Ok(Value::Number(value.chars().count() as f64)),
+        Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+        value => Err(format!(
+            "Can only take the length of strings and lists, got {}.",
+            value.type_name()
+        )),
+    }
+}
+
+/// Characters `start` up to but not including `end`, counted like `len`
+fn substring(_interp: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    let value = expect_string("substring", &args[0])?;
+    let start = expect_index(&args[1])?;
+    let end = expect_index(&args[2])?;
+
+    let len = value.chars().count();
+    if start > end || end > len {
+        return Err(format!(
+            "Substring range {}..{} is out of bounds for length {}.",
+            start, end, len
+        ));
+    }
+
+    Ok(Value::Str(
+        value.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+fn upper(_interp: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(expect_string("upper", &args[0])?.to_uppercase()))
+}
+
+fn lower(_interp: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(expect_string("lower", &args[0])?.to_lowercase()))
+}
+
+/// Reads a line from the interpreter's input, `nil` once it runs out
+fn input(interp: &mut Interpreter, _args: &[Value]) -> Result<Value, String> {
+    match interp.read_line() {
+        Ok(Some(line)) => Ok(Value::Str(line)),
+        Ok(None) => Ok(Value::Nil),
+        Err(err) => Err(format!("Could not read input: {}.", err)),
+    }
+}
+
+/// Name of the argument's type, e.g. `"number"`
+fn type_of(_interp: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(args[0].type_name().to_string()))
+}
+
+/// Fails with the optional message, or a default one, unless the condition is truthy
+fn assert(_interp: &mut Interpreter, args: &[Value]) -> Result<Value, String> {
+    if is_truthy(&args[0]) {
+        return Ok(Value::Nil);
+    }
+
+    match args.get(1) {
+        Some(message) => Err(message.to_string()),
+        None => Err("Assertion failed".to_string()),
+    }
+}
+
+fn expect_string<'a>(function: &str, value: &'a Value) -> Result<&'a str, String> {
+    match value {
+        Value::Str(value) => Ok(value),
+        value => Err(format!(
+            "{}() expects a string, got {}.",
+            function,
+            value.type_name()
+        )),
+    }
+}
+
+/// A whole, non-negative number usable as a position in a string
+fn expect_index(value: &Value) -> Result<usize, String> {
+    match value {
+        Value::Number(index) if index.fract() == 0.0 && *index >= 0.0 => Ok(*index as usize),
+        Value::Number(index) => Err(format!(
+            "Index must be a non-negative integer, got {}.",
+            index
+        )),
+        value => Err(format!(
+            "Index must be a number, got {}.",
+            value.type_name()
+        )),
+    }
+}
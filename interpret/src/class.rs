@@ -0,0 +1,102 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+use crate::callable::LoxCallable;
+use crate::function::LoxFunction;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::scanner::Token;
+use crate::value::Value;
+
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    /// Handed to the instances it creates
+    this: Weak<LoxClass>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Rc<Self> {
+        Rc::new_cyclic(|this| LoxClass {
+            name,
+            superclass,
+            methods,
+            this: this.clone(),
+        })
+    }
+
+    /// Looks through the superclass chain too, the nearest definition wins
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match (self.methods.get(name), &self.superclass) {
+            (Some(method), _) => Some(method.clone()),
+            (None, Some(superclass)) => superclass.find_method(name),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Calling a class creates a new instance of it and runs `init` on it
+impl LoxCallable for LoxClass {
+    /// Takes the arguments of `init`
+    fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let class = self.this.upgrade().expect("class outlives its calls");
+        let instance = Rc::new(RefCell::new(LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }));
+
+        if let Some(init) = self.find_method("init") {
+            init.bind(instance.clone()).call(interp, args)?;
+        }
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, Value>,
+}
+
+impl LoxInstance {
+    /// Looks up a field, or else a method bound to `instance`. Fields shadow methods
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = instance.borrow().fields.get(&*name.text()) {
+            return Ok(value.clone());
+        }
+
+        match instance.borrow().class.find_method(&name.text()) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(instance.clone())))),
+            None => Err(RuntimeError::new(
+                name,
+                &format!("Undefined property '{}'.", name.text()),
+            )),
+        }
+    }
+
+    /// Creates the field if it doesn't exist yet
+    pub fn set(&mut self, name: &Token, value: Value) {
+        self.fields.insert(name.text().into_owned(), value);
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::interpreter::RuntimeError;
+use crate::parser::ParseError;
+use crate::resolver::ResolveError;
+use crate::scanner::ScanError;
+use crate::IoError;
+
+/// Error that can be pointed at in the source
+pub trait Diagnostic {
+    fn message(&self) -> &str;
+
+    /// 1-based line and column, a line of 0 means the location is unknown
+    fn location(&self) -> (usize, usize);
+
+    fn render(&self, source: &str) -> String {
+        let (line, column) = self.location();
+        render(source, line, column, self.message())
+    }
+}
+
+/// Formats an error like rustc does, quoting the source line with a `^` under the column:
+///
+/// ```text
+/// error: Operand must be a number, got nil.
+///  --> 1:7
+///   |
+/// 1 | print -nil;
+///   |       ^
+/// ```
+pub fn render(source: &str, line: usize, column: usize, message: &str) -> String {
+    let mut output = format!("error: {}", message);
+
+    let text = match line
+        .checked_sub(1)
+        .and_then(|index| source.lines().nth(index))
+    {
+        Some(text) => text,
+        None => return output,
+    };
+    let gutter = " ".repeat(line.to_string().len());
+    // Tabs are kept so the caret lines up however wide they are displayed
+    let padding: String = text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    output.push_str(&format!("\n{}--> {}:{}", gutter, line, column));
+    output.push_str(&format!("\n{} |", gutter));
+    output.push_str(&format!("\n{} | {}", line, text));
+    output.push_str(&format!("\n{} | {}^", gutter, padding));
+    output
+}
+
+impl Diagnostic for ScanError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn location(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+}
+
+impl Diagnostic for ParseError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn location(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+}
+
+impl Diagnostic for ResolveError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn location(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+}
+
+impl Diagnostic for RuntimeError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn location(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+}
+
+impl Diagnostic for IoError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn location(&self) -> (usize, usize) {
+        (0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn caret_under_column() {
+        let source = "var a = 1;\nprint a +;\n";
+        let errors = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap_err();
+
+        assert_eq!(
+            errors[0].render(source),
+            "error: Expect expression.\n --> 2:10\n  |\n2 | print a +;\n  |          ^"
+        );
+    }
+
+    #[test]
+    fn wide_gutter_and_tabs() {
+        let source = format!("{}\tx = @;", "\n".repeat(10));
+
+        assert_eq!(
+            render(&source, 11, 6, "Unexpected character '@'"),
+            "error: Unexpected character '@'\n  --> 11:6\n   |\n11 | \tx = @;\n   | \t    ^"
+        );
+    }
+
+    #[test]
+    fn unknown_location() {
+        assert_eq!(render("print 1;", 0, 0, "Oops."), "error: Oops.");
+    }
+}
@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::RuntimeError;
+use crate::scanner::Token;
+use crate::value::Value;
+
+/// Variable bindings of a scope
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    /// Scope this one is nested in, `None` for the global scope
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Binds `name`, redefining an existing variable is allowed
+    pub fn define(&mut self, name: String, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        match (self.values.get(&*name.text()), &self.enclosing) {
+            (Some(value), _) => Ok(value.clone()),
+            (None, Some(enclosing)) => enclosing.borrow().get(name),
+            (None, None) => Err(Self::undefined(name)),
+        }
+    }
+
+    /// Assigns to an existing variable, assignment can't create a new one
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        match self.values.get_mut(&*name.text()) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => Err(Self::undefined(name)),
+            },
+        }
+    }
+
+    /// Reads `name` from the scope `distance` levels up, where the resolver found it
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        match (distance, &self.enclosing) {
+            (0, _) => self
+                .values
+                .get(&*name.text())
+                .cloned()
+                .ok_or_else(|| Self::undefined(name)),
+            (_, Some(enclosing)) => enclosing.borrow().get_at(distance - 1, name),
+            (_, None) => Err(Self::undefined(name)),
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        match (distance, &self.enclosing) {
+            (0, _) => match self.values.get_mut(&*name.text()) {
+                Some(slot) => {
+                    *slot = value;
+                    Ok(())
+                }
+                None => Err(Self::undefined(name)),
+            },
+            (_, Some(enclosing)) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            (_, None) => Err(Self::undefined(name)),
+        }
+    }
+
+    fn undefined(name: &Token) -> RuntimeError {
+        RuntimeError::new(name, &format!("Undefined variable '{}'.", name.text()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::*;
+    use crate::scanner::TokenType;
+
+    fn identifier(name: &str, line: usize) -> Token {
+        Token {
+            kind: TokenType::Identifier(name.into()),
+            span: 0..name.len(),
+            line,
+            column: 1,
+        }
+    }
+
+    #[test]
+    fn define_and_get() {
+        let mut env = Environment::new();
+        env.define("a".to_string(), Value::Number(1.0));
+
+        assert_eq!(env.get(&identifier("a", 1)), Ok(Value::Number(1.0)));
+
+        env.assign(&identifier("a", 1), Value::Nil).unwrap();
+        assert_eq!(env.get(&identifier("a", 1)), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn enclosing_scope() {
+        let global = Rc::new(RefCell::new(Environment::new()));
+        global
+            .borrow_mut()
+            .define("a".to_string(), Value::Number(1.0));
+        let mut local = Environment::with_enclosing(global.clone());
+
+        assert_eq!(local.get(&identifier("a", 1)), Ok(Value::Number(1.0)));
+
+        local.assign(&identifier("a", 1), Value::Nil).unwrap();
+        local.define("b".to_string(), Value::Bool(true));
+        assert_eq!(global.borrow().get(&identifier("a", 1)), Ok(Value::Nil));
+        assert!(global.borrow().get(&identifier("b", 1)).is_err());
+    }
+
+    #[test]
+    fn resolved_distance() {
+        let global = Rc::new(RefCell::new(Environment::new()));
+        global
+            .borrow_mut()
+            .define("a".to_string(), Value::Number(1.0));
+        let mut local = Environment::with_enclosing(global.clone());
+        local.define("a".to_string(), Value::Number(2.0));
+
+        assert_eq!(local.get_at(0, &identifier("a", 1)), Ok(Value::Number(2.0)));
+        assert_eq!(local.get_at(1, &identifier("a", 1)), Ok(Value::Number(1.0)));
+
+        local.assign_at(1, &identifier("a", 1), Value::Nil).unwrap();
+        assert_eq!(global.borrow().get(&identifier("a", 1)), Ok(Value::Nil));
+        assert_eq!(local.get(&identifier("a", 1)), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn get_undefined() {
+        let err = Environment::new().get(&identifier("a", 3)).unwrap_err();
+
+        assert_eq!(err.message, "Undefined variable 'a'.");
+        assert_eq!(err.line, 3);
+    }
+
+    #[test]
+    fn assign_undefined() {
+        let err = Environment::new()
+            .assign(&identifier("b", 5), Value::Bool(true))
+            .unwrap_err();
+
+        assert_eq!(err.message, "Undefined variable 'b'.");
+        assert_eq!(err.line, 5);
+    }
+}
@@ -0,0 +1,157 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::scanner::Token;
+use crate::stmt::Stmt;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
+pub enum LiteralValue {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Nil,
+}
+
+/// With the `serde` feature this serializes as `{"type": "<variant>", "value": <fields>}`, tokens
+/// as in `--tokens-json` with their kind tagged the same way. Resolved depths are internal and
+/// left out
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
+pub enum Expr {
+    Literal(LiteralValue),
+    Grouping(Box<Expr>),
+    Unary {
+        op: Token,
+        right: Box<Expr>,
+    },
+    Binary {
+        left: Box<Expr>,
+        op: Token,
+        right: Box<Expr>,
+    },
+    Variable {
+        name: Token,
+        /// Scopes between the use and the declaration, set by the resolver and `None` for globals
+        #[cfg_attr(feature = "serde", serde(skip))]
+        depth: Cell<Option<usize>>,
+    },
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+        /// Same as for `Variable`
+        #[cfg_attr(feature = "serde", serde(skip))]
+        depth: Cell<Option<usize>>,
+    },
+    /// `cond ? then_expr : else_expr`, only the branch that's taken is evaluated
+    Ternary {
+        cond: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+    },
+    /// `and`/`or`, kept apart from `Binary` since they short-circuit
+    Logical {
+        left: Box<Expr>,
+        op: Token,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        /// Closing paren, used to locate errors from the call
+        paren: Token,
+        args: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    /// Anonymous function, `fun (params) { body }`
+    Function {
+        /// Stands in for the name when locating errors and printing the function
+        keyword: Token,
+        /// Same as for `Stmt::Function`
+        params: Rc<[Token]>,
+        body: Rc<Vec<Stmt>>,
+    },
+    /// `[a, b]`
+    List(Vec<Expr>),
+    /// `list[index]`
+    Index {
+        object: Box<Expr>,
+        /// Closing bracket, used to locate indexing errors
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// `list[index] = value`
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    This {
+        keyword: Token,
+        /// Same as for `Variable`
+        #[cfg_attr(feature = "serde", serde(skip))]
+        depth: Cell<Option<usize>>,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+        /// Same as for `Variable`
+        #[cfg_attr(feature = "serde", serde(skip))]
+        depth: Cell<Option<usize>>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::scanner::TokenType;
+
+    #[test]
+    fn nested_expression() {
+        // -123 * (45.67)
+        let expr = Expr::Binary {
+            left: Box::new(Expr::Unary {
+                op: Token {
+                    kind: TokenType::Minus,
+                    span: 0..1,
+                    line: 1,
+                    column: 1,
+                },
+                right: Box::new(Expr::Literal(LiteralValue::Number(123.0))),
+            }),
+            op: Token {
+                kind: TokenType::Asterisk,
+                span: 5..6,
+                line: 1,
+                column: 6,
+            },
+            right: Box::new(Expr::Grouping(Box::new(Expr::Literal(
+                LiteralValue::Number(45.67),
+            )))),
+        };
+
+        match expr {
+            Expr::Binary { left, op, right } => {
+                assert!(matches!(*left, Expr::Unary { .. }));
+                assert_eq!(op.kind, TokenType::Asterisk);
+                assert_eq!(
+                    *right,
+                    Expr::Grouping(Box::new(Expr::Literal(LiteralValue::Number(45.67))))
+                );
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::LoxCallable;
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::interpreter::{ExecSignal, Interpreter, RuntimeError};
+use crate::scanner::{Token, TokenType};
+use crate::stmt::Stmt;
+use crate::value::Value;
+
+/// Function declared in Lox code
+pub struct LoxFunction {
+    /// The `fun` keyword for anonymous functions
+    name: Token,
+    /// Shared with the declaration and with bound copies of a method
+    params: Rc<[Token]>,
+    body: Rc<Vec<Stmt>>,
+    /// Scope the function was declared in
+    closure: Rc<RefCell<Environment>>,
+    /// `init` methods always return `this`
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        name: Token,
+        params: Rc<[Token]>,
+        body: Rc<Vec<Stmt>>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            name,
+            params,
+            body,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Copy of this method with `this` bound to `instance`
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define("this".to_string(), Value::Instance(instance));
+
+        LoxFunction {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            body: self.body.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    /// The instance a bound initializer is bound to
+    fn this(&self) -> Result<Value, RuntimeError> {
+        let this = Token {
+            kind: TokenType::This,
+            ..self.name.clone()
+        };
+        self.closure.borrow().get_at(0, &this)
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        for (param, arg) in self.params.iter().zip(args) {
+            environment.define(param.text().into_owned(), arg);
+        }
+
+        interp.enter_call()?;
+        let result = interp.execute_block(&self.body, Rc::new(RefCell::new(environment)));
+        interp.exit_call();
+
+        match result {
+            Ok(()) | Err(ExecSignal::Return(_)) if self.is_initializer => self.this(),
+            Ok(()) => Ok(Value::Nil),
+            Err(ExecSignal::Return(value)) => Ok(value),
+            Err(ExecSignal::Error(err)) => Err(err),
+            Err(ExecSignal::Break | ExecSignal::Continue) => {
+                unreachable!("the resolver keeps break and continue inside loops")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.kind {
+            TokenType::Fun => f.write_str("<anonymous fn>"),
+            _ => write!(f, "<fn {}>", self.name.text()),
+        }
+    }
+}
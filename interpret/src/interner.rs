@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Hands out one shared `Rc<str>` per distinct string, so repeated identifiers aren't copied
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// Returns the handle for `text`, only allocating the first time it's seen
+    pub fn intern(&mut self, text: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.get(text) {
+            return Rc::clone(interned);
+        }
+
+        let interned: Rc<str> = Rc::from(text);
+        self.strings.insert(Rc::clone(&interned));
+        interned
+    }
+
+    /// Number of distinct strings interned so far
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interner::*;
+
+    #[test]
+    fn same_text_shares_storage() {
+        let mut interner = Interner::new();
+
+        let first = interner.intern("count");
+        let second = interner.intern("count");
+
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn different_text_is_kept_apart() {
+        let mut interner = Interner::new();
+
+        let a = interner.intern("a");
+        let b = interner.intern("b");
+
+        assert_eq!(&*a, "a");
+        assert_eq!(&*b, "b");
+        assert_eq!(interner.len(), 2);
+    }
+}
@@ -0,0 +1,1519 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+use std::thread;
+
+use crate::callable::{self, LoxCallable};
+use crate::class::{LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::expr::{Expr, LiteralValue};
+use crate::function::LoxFunction;
+use crate::scanner::{Token, TokenType};
+use crate::stmt::Stmt;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    /// Location of the token blamed for the error, 0 when there isn't one
+    pub line: usize,
+    pub column: usize,
+}
+
+impl RuntimeError {
+    /// Error blamed on `token`, usually the operator being applied
+    pub fn new(token: &Token, message: &str) -> Self {
+        RuntimeError {
+            message: message.to_string(),
+            line: token.line,
+            column: token.column,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.message)
+    }
+}
+
+/// Default limit on nested Lox function calls
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// Native stack size to run the interpreter with, each Lox call takes several evaluator frames
+/// which are large in debug builds, so the default stack of a thread overflows long before
+/// `MAX_CALL_DEPTH`
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Runs `f` on a thread with a stack of `STACK_SIZE`, blocking until it's done. Panics from `f`
+/// carry on in the caller
+pub fn with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    with_stack_alongside(f, || {})
+}
+
+/// Like `with_stack`, running `alongside` on the calling thread in the meantime
+pub fn with_stack_alongside<T: Send>(f: impl FnOnce() -> T + Send, alongside: impl FnOnce()) -> T {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("failed to spawn interpreter thread");
+        alongside();
+        handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// Why statement execution stopped early
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecSignal {
+    Error(RuntimeError),
+    /// Unwinds to the call of the enclosing function
+    Return(Value),
+    /// Unwind to the innermost loop
+    Break,
+    Continue,
+}
+
+impl From<RuntimeError> for ExecSignal {
+    fn from(err: RuntimeError) -> Self {
+        ExecSignal::Error(err)
+    }
+}
+
+/// Lox only treats `nil` and `false` as falsey
+pub fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Nil | Value::Bool(false))
+}
+
+/// Output sink that can still be read after an interpreter takes ownership of a clone of it
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        SharedBuffer::default()
+    }
+
+    /// Everything written so far, invalid UTF-8 is replaced
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads stdin a line at a time without holding on to the lock, so the REPL and `input()` can
+/// both read from it without either buffering lines meant for the other
+#[derive(Default)]
+pub struct StdinReader {
+    line: String,
+    /// Bytes of `line` already consumed
+    position: usize,
+}
+
+impl Read for StdinReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for StdinReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position == self.line.len() {
+            self.line.clear();
+            self.position = 0;
+            io::stdin().read_line(&mut self.line)?;
+        }
+        Ok(&self.line.as_bytes()[self.position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt;
+    }
+}
+
+/// Tree-walking interpreter, evaluates the AST directly. Local variables are looked up at the
+/// depth the resolver recorded, so it has to run over statements before they are interpreted
+pub struct Interpreter {
+    /// Outermost scope, holds the natives
+    globals: Rc<RefCell<Environment>>,
+    /// Innermost scope of the code being executed
+    environment: Rc<RefCell<Environment>>,
+    /// Where `print` statements write to
+    output: Box<dyn Write>,
+    /// Where `input()` reads lines from
+    input: Box<dyn BufRead>,
+    /// Location of the most recent call, natives blame their errors on it
+    call_line: usize,
+    call_column: usize,
+    /// Lox functions currently being called
+    call_depth: usize,
+    max_call_depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::with_output(Box::new(io::stdout()))
+    }
+
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        for native in callable::natives() {
+            globals
+                .borrow_mut()
+                .define(native.name.to_string(), Value::Callable(Rc::new(native)));
+        }
+
+        Interpreter {
+            environment: globals.clone(),
+            globals,
+            output,
+            input: Box::new(StdinReader::default()),
+            call_line: 0,
+            call_column: 0,
+            call_depth: 0,
+            max_call_depth: MAX_CALL_DEPTH,
+        }
+    }
+
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Replaces stdin as the source of `input()`
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = input;
+    }
+
+    /// Next line of input without its line ending, `None` at the end of it
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// Tracks a Lox function call, failing once calls nest deeper than the limit. Must be paired
+    /// with `exit_call` when it succeeds
+    pub fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        if self.call_depth >= self.max_call_depth {
+            return Err(self.error_at_call("Stack overflow".to_string()));
+        }
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    pub fn exit_call(&mut self) {
+        self.call_depth -= 1;
+    }
+
+    /// Error blamed on the most recent call
+    pub fn error_at_call(&self, message: String) -> RuntimeError {
+        RuntimeError {
+            message,
+            line: self.call_line,
+            column: self.call_column,
+        }
+    }
+
+    /// Runs `statements` in order, stopping at the first runtime error which is returned for the
+    /// caller to report
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(ExecSignal::Error(err)) => return Err(err),
+                // Only reachable if the resolver was skipped, ends the program like falling off
+                // the end of it would
+                Err(ExecSignal::Return(_) | ExecSignal::Break | ExecSignal::Continue) => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), ExecSignal> {
+        match stmt {
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Print(expr) => {
+                let value = self.evaluate(expr)?;
+                writeln!(self.output, "{}", value).map_err(|err| RuntimeError {
+                    message: format!("Failed to write output: {}.", err),
+                    line: 0,
+                    column: 0,
+                })?;
+            }
+            Stmt::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Value::Nil,
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(name.text().into_owned(), value);
+            }
+            Stmt::Block(statements) => {
+                let environment = Environment::with_enclosing(self.environment.clone());
+                self.execute_block(statements, Rc::new(RefCell::new(environment)))?;
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if is_truthy(&self.evaluate(condition)?) {
+                    self.execute(then_branch)?;
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)?;
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                while is_truthy(&self.evaluate(condition)?) {
+                    match self.execute(body) {
+                        Ok(()) | Err(ExecSignal::Continue) => {}
+                        Err(ExecSignal::Break) => break,
+                        Err(signal) => return Err(signal),
+                    }
+
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
+                }
+            }
+            Stmt::Break { .. } => return Err(ExecSignal::Break),
+            Stmt::Continue { .. } => return Err(ExecSignal::Continue),
+            Stmt::Function { name, params, body } => {
+                let function = LoxFunction::new(
+                    name.clone(),
+                    params.clone(),
+                    body.clone(),
+                    self.environment.clone(),
+                    false,
+                );
+                self.environment
+                    .borrow_mut()
+                    .define(name.text().into_owned(), Value::Callable(Rc::new(function)));
+            }
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+                return Err(ExecSignal::Return(value));
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let superclass = match superclass {
+                    Some(expr) => match self.evaluate(expr)? {
+                        Value::Class(class) => Some(class),
+                        _ => {
+                            return Err(
+                                RuntimeError::new(name, "Superclass must be a class.").into()
+                            )
+                        }
+                    },
+                    None => None,
+                };
+
+                // Methods of a subclass close over a scope holding `super`
+                let closure = match &superclass {
+                    Some(superclass) => {
+                        let mut environment = Environment::with_enclosing(self.environment.clone());
+                        environment.define("super".to_string(), Value::Class(superclass.clone()));
+                        Rc::new(RefCell::new(environment))
+                    }
+                    None => self.environment.clone(),
+                };
+
+                let methods = methods
+                    .iter()
+                    .filter_map(|method| match method {
+                        Stmt::Function { name, params, body } => Some((
+                            name.text().into_owned(),
+                            Rc::new(LoxFunction::new(
+                                name.clone(),
+                                params.clone(),
+                                body.clone(),
+                                closure.clone(),
+                                name.text() == "init",
+                            )),
+                        )),
+                        _ => None,
+                    })
+                    .collect();
+                let class = LoxClass::new(name.text().into_owned(), superclass, methods);
+                self.environment
+                    .borrow_mut()
+                    .define(name.text().into_owned(), Value::Class(class));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `statements` in `environment`, the current scope is restored afterwards even if one of
+    /// them fails
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), ExecSignal> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        result
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Literal(value) => Ok(match value {
+                LiteralValue::Number(value) => Value::Number(*value),
+                LiteralValue::String(value) => Value::Str(value.clone()),
+                LiteralValue::Bool(value) => Value::Bool(*value),
+                LiteralValue::Nil => Value::Nil,
+            }),
+            Expr::Grouping(inner) => self.evaluate(inner),
+            Expr::Unary { op, right } => {
+                let right = self.evaluate(right)?;
+                self.unary(op, right)
+            }
+            Expr::Binary { left, op, right } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.binary(left, op, right)
+            }
+            Expr::Variable { name, depth } => self.look_up_variable(name, depth.get()),
+            Expr::This { keyword, depth } => self.look_up_variable(keyword, depth.get()),
+            Expr::Assign { name, value, depth } => {
+                let value = self.evaluate(value)?;
+                match depth.get() {
+                    Some(distance) => {
+                        self.environment
+                            .borrow_mut()
+                            .assign_at(distance, name, value.clone())?
+                    }
+                    None => self.globals.borrow_mut().assign(name, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Logical { left, op, right } => {
+                let left = self.evaluate(left)?;
+                // The result is whichever operand decided it, not a coerced bool
+                let decided = match op.kind {
+                    TokenType::Or => is_truthy(&left),
+                    _ => !is_truthy(&left),
+                };
+                if decided {
+                    Ok(left)
+                } else {
+                    self.evaluate(right)
+                }
+            }
+            Expr::Call {
+                callee,
+                paren,
+                args,
+            } => {
+                let callee = self.evaluate(callee)?;
+                let args = args
+                    .iter()
+                    .map(|arg| self.evaluate(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(callee, paren, args)
+            }
+            Expr::Get { object, name } => match self.evaluate(object)? {
+                Value::Instance(instance) => LoxInstance::get(&instance, name),
+                _ => Err(RuntimeError::new(name, "Only instances have properties.")),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                let instance = match self.evaluate(object)? {
+                    Value::Instance(instance) => instance,
+                    _ => return Err(RuntimeError::new(name, "Only instances have fields.")),
+                };
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(name, value.clone());
+                Ok(value)
+            }
+            Expr::Function {
+                keyword,
+                params,
+                body,
+            } => Ok(Value::Callable(Rc::new(LoxFunction::new(
+                keyword.clone(),
+                params.clone(),
+                body.clone(),
+                self.environment.clone(),
+                false,
+            )))),
+            Expr::Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                if is_truthy(&self.evaluate(cond)?) {
+                    self.evaluate(then_expr)
+                } else {
+                    self.evaluate(else_expr)
+                }
+            }
+            Expr::List(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::List(Rc::new(RefCell::new(values))))
+            }
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let list = Interpreter::list(self.evaluate(object)?, bracket)?;
+                let index = self.evaluate(index)?;
+                let list = list.borrow();
+                let position = Interpreter::position(&index, list.len(), bracket)?;
+                Ok(list[position].clone())
+            }
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => {
+                let list = Interpreter::list(self.evaluate(object)?, bracket)?;
+                let index = self.evaluate(index)?;
+                let value = self.evaluate(value)?;
+                let position = Interpreter::position(&index, list.borrow().len(), bracket)?;
+                list.borrow_mut()[position] = value.clone();
+                Ok(value)
+            }
+            Expr::Super {
+                keyword,
+                method,
+                depth,
+            } => {
+                // The resolver puts `this` in the scope right inside the one holding `super`.
+                // Without it there's no telling where either is
+                let unresolved = || RuntimeError::new(keyword, "Can't resolve 'super'.");
+                let distance = depth.get().filter(|&distance| distance > 0);
+                let distance = distance.ok_or_else(unresolved)?;
+                let superclass = match self.environment.borrow().get_at(distance, keyword)? {
+                    Value::Class(class) => class,
+                    _ => return Err(unresolved()),
+                };
+                let this = Token {
+                    kind: TokenType::This,
+                    ..keyword.clone()
+                };
+                let instance = match self.environment.borrow().get_at(distance - 1, &this)? {
+                    Value::Instance(instance) => instance,
+                    _ => return Err(unresolved()),
+                };
+
+                match superclass.find_method(&method.text()) {
+                    Some(found) => Ok(Value::Callable(Rc::new(found.bind(instance)))),
+                    None => Err(RuntimeError::new(
+                        method,
+                        &format!("Undefined property '{}'.", method.text()),
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Reads a variable from the scope the resolver found it in, unresolved ones are global
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, RuntimeError> {
+        match depth {
+            Some(distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn call(
+        &mut self,
+        callee: Value,
+        paren: &Token,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let callable: Rc<dyn LoxCallable> = match callee {
+            Value::Callable(callable) => callable,
+            Value::Class(class) => class,
+            _ => {
+                return Err(RuntimeError::new(
+                    paren,
+                    "Can only call functions and classes.",
+                ))
+            }
+        };
+
+        let (arity, max_arity) = (callable.arity(), callable.max_arity());
+        if args.len() < arity || args.len() > max_arity {
+            let expected = if arity == max_arity {
+                arity.to_string()
+            } else {
+                format!("{} to {}", arity, max_arity)
+            };
+            return Err(RuntimeError::new(
+                paren,
+                &format!("Expected {} arguments but got {}.", expected, args.len()),
+            ));
+        }
+
+        self.call_line = paren.line;
+        self.call_column = paren.column;
+        callable.call(self, args)
+    }
+
+    fn unary(&mut self, op: &Token, right: Value) -> Result<Value, RuntimeError> {
+        match op.kind {
+            TokenType::Minus => match right {
+                Value::Number(value) => Ok(Value::Number(-value)),
+                other => Err(RuntimeError::new(
+                    op,
+                    &format!("Operand must be a number, got {}.", other.type_name()),
+                )),
+            },
+            TokenType::Bang => Ok(Value::Bool(!is_truthy(&right))),
+            _ => unreachable!("parser only produces '-' and '!' unary operators"),
+        }
+    }
+
+    fn binary(&mut self, left: Value, op: &Token, right: Value) -> Result<Value, RuntimeError> {
+        match op.kind {
+            TokenType::EqualEqual => return Ok(Value::Bool(left == right)),
+            TokenType::BangEqual => return Ok(Value::Bool(left != right)),
+            TokenType::Plus => return Self::add(left, op, right),
+            _ => {}
+        }
+
+        let (left, right) = match (left, right) {
+            (Value::Number(left), Value::Number(right)) => (left, right),
+            (left, right) => {
+                return Err(RuntimeError::new(
+                    op,
+                    &format!(
+                        "Operands must be numbers, got {} and {}.",
+                        left.type_name(),
+                        right.type_name()
+                    ),
+                ))
+            }
+        };
+
+        Ok(match op.kind {
+            TokenType::Minus => Value::Number(left - right),
+            TokenType::Asterisk => Value::Number(left * right),
+            TokenType::Slash if right == 0.0 => {
+                return Err(RuntimeError::new(op, "Division by zero"))
+            }
+            TokenType::Slash => Value::Number(left / right),
+            // Takes the sign of the dividend, like `fmod`
+            TokenType::Percent if right == 0.0 => {
+                return Err(RuntimeError::new(op, "Division by zero"))
+            }
+            TokenType::Percent => Value::Number(left % right),
+            TokenType::Greater => Value::Bool(left > right),
+            TokenType::GreaterEqual => Value::Bool(left >= right),
+            TokenType::Less => Value::Bool(left < right),
+            TokenType::LessEqual => Value::Bool(left <= right),
+            _ => unreachable!("not a binary operator: {:?}", op.kind),
+        })
+    }
+
+    fn list(value: Value, bracket: &Token) -> Result<Rc<RefCell<Vec<Value>>>, RuntimeError> {
+        match value {
+            Value::List(list) => Ok(list),
+            value => Err(RuntimeError::new(
+                bracket,
+                &format!("Only lists can be indexed, got {}.", value.type_name()),
+            )),
+        }
+    }
+
+    /// Checks that `index` is a whole number inside a list of length `len`
+    fn position(index: &Value, len: usize, bracket: &Token) -> Result<usize, RuntimeError> {
+        let index = match index {
+            Value::Number(index) if index.fract() == 0.0 => *index,
+            Value::Number(index) => {
+                return Err(RuntimeError::new(
+                    bracket,
+                    &format!("List index must be an integer, got {}.", index),
+                ))
+            }
+            index => {
+                return Err(RuntimeError::new(
+                    bracket,
+                    &format!("List index must be a number, got {}.", index.type_name()),
+                ))
+            }
+        };
+
+        if index < 0.0 || index >= len as f64 {
+            return Err(RuntimeError::new(
+                bracket,
+                &format!("List index {} out of bounds for length {}.", index, len),
+            ));
+        }
+
+        Ok(index as usize)
+    }
+
+    /// Adds numbers or concatenates strings. Mixed operands are an error rather than being coerced
+    /// to strings, so `"a" + 1` fails
+    fn add(left: Value, op: &Token, right: Value) -> Result<Value, RuntimeError> {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
+            (Value::Str(left), Value::Str(right)) => Ok(Value::Str(left + &right)),
+            (left, right) => Err(RuntimeError::new(
+                op,
+                &format!(
+                    "Operands must be two numbers or two strings, got {} and {}.",
+                    left.type_name(),
+                    right.type_name()
+                ),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let expr = Parser::new(Scanner::new(source).scan_tokens())
+            .parse_expression()
+            .unwrap();
+        Interpreter::new().evaluate(&expr)
+    }
+
+    fn parse_statements(source: &str) -> Vec<Stmt> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        Resolver::new().resolve(&statements).unwrap();
+        statements
+    }
+
+    #[test]
+    fn super_without_resolver() {
+        let statements = Parser::new(Scanner::new("super.f;").scan_tokens())
+            .parse()
+            .unwrap();
+
+        let err = Interpreter::with_output(Box::new(io::sink()))
+            .interpret(&statements)
+            .unwrap_err();
+
+        assert_eq!(err.message, "Can't resolve 'super'.");
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(Value::Number(7.0)));
+    }
+
+    #[test]
+    fn negated_equality() {
+        assert_eq!(evaluate("!(1 == 1)"), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(is_truthy(&Value::Number(0.0)));
+        assert!(is_truthy(&Value::Str(String::new())));
+        assert!(is_truthy(&Value::Bool(true)));
+        assert!(!is_truthy(&Value::Nil));
+        assert!(!is_truthy(&Value::Bool(false)));
+        assert_eq!(evaluate("!0"), Ok(Value::Bool(false)));
+        assert_eq!(evaluate("!nil"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn negate_string() {
+        let err = evaluate("\n-\"a\"").unwrap_err();
+        assert_eq!(err.message, "Operand must be a number, got string.");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn compare_number_to_string() {
+        let err = evaluate("1\n\n<\n\"b\"").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Operands must be numbers, got number and string."
+        );
+        assert_eq!(err.line, 3);
+        assert_eq!(
+            err.to_string(),
+            "[line 3] Error: Operands must be numbers, got number and string."
+        );
+    }
+
+    #[test]
+    fn add_numbers() {
+        assert_eq!(evaluate("1 + 2"), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn concatenate_strings() {
+        assert_eq!(
+            evaluate("\"foo\" + \"bar\""),
+            Ok(Value::Str("foobar".to_string()))
+        );
+    }
+
+    #[test]
+    fn add_string_to_number() {
+        let err = evaluate("\"foo\" + 1").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Operands must be two numbers or two strings, got string and number."
+        );
+    }
+
+    #[test]
+    fn division() {
+        assert_eq!(evaluate("6 / 2"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate("1 / 0").unwrap_err().message, "Division by zero");
+    }
+
+    #[test]
+    fn lambda_in_variable() {
+        assert_eq!(
+            run("var add = fun (a, b) { return a + b; }; print add(1, 2); print add;"),
+            Ok("3\n<anonymous fn>\n".to_string())
+        );
+    }
+
+    #[test]
+    fn functions_share_the_ast() {
+        let statements = parse_statements("fun f() { print 1; } var fs = [f, f, fun () {}];");
+        let mut interpreter = Interpreter::with_output(Box::new(io::sink()));
+        interpreter.interpret(&statements).unwrap();
+
+        // The declaration and the one function made from it
+        match &statements[0] {
+            Stmt::Function { params, body, .. } => {
+                assert_eq!(Rc::strong_count(params), 2);
+                assert_eq!(Rc::strong_count(body), 2);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn lambda_as_argument() {
+        let source = "
+            fun map(list, f) {
+              for (var i = 0; i < len(list); i = i + 1) list[i] = f(list[i]);
+              return list;
+            }
+            var offset = 10;
+            print map([1, 2, 3], fun (x) { return x + offset; });
+        ";
+        assert_eq!(run(source), Ok("[11, 12, 13]\n".to_string()));
+    }
+
+    #[test]
+    fn lambda_closes_over_locals() {
+        let source = "
+            fun counter() {
+              var count = 0;
+              return fun () { count = count + 1; return count; };
+            }
+            var next = counter();
+            next();
+            print next();
+        ";
+        assert_eq!(run(source), Ok("2\n".to_string()));
+    }
+
+    #[test]
+    fn ternary() {
+        assert_eq!(evaluate("true ? 1 : 2"), Ok(Value::Number(1.0)));
+        assert_eq!(evaluate("nil ? 1 : 2"), Ok(Value::Number(2.0)));
+        assert_eq!(evaluate("false ? 1 : true ? 2 : 3"), Ok(Value::Number(2.0)));
+        assert_eq!(evaluate("false ? 1 : nil ? 2 : 3"), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn ternary_skips_untaken_branch() {
+        assert_eq!(
+            run("var a = 0; true ? a = 1 : (a = 2); print a; print false ? 1 / 0 : \"ok\";"),
+            Ok("1\nok\n".to_string())
+        );
+    }
+
+    #[test]
+    fn modulo() {
+        assert_eq!(evaluate("10 % 3"), Ok(Value::Number(1.0)));
+        assert_eq!(evaluate("5.5 % 2"), Ok(Value::Number(1.5)));
+        assert_eq!(evaluate("-7 % 3"), Ok(Value::Number(-1.0)));
+        assert_eq!(evaluate("2 + 7 % 4 * 2"), Ok(Value::Number(8.0)));
+        assert_eq!(evaluate("5 % 0").unwrap_err().message, "Division by zero");
+        assert_eq!(
+            evaluate("\"a\" % 2").unwrap_err().message,
+            "Operands must be numbers, got string and number."
+        );
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(evaluate("-1 < 2"), Ok(Value::Bool(true)));
+        assert_eq!(evaluate("\"a\" == \"a\""), Ok(Value::Bool(true)));
+        assert_eq!(evaluate("nil != false"), Ok(Value::Bool(true)));
+    }
+
+    /// Runs `source` and returns everything it printed
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let output = SharedBuffer::default();
+        Interpreter::with_output(Box::new(output.clone())).interpret(&parse_statements(source))?;
+        Ok(output.contents())
+    }
+
+    #[test]
+    fn var_and_print() {
+        assert_eq!(run("var x = 2; print x + 1;"), Ok("3\n".to_string()));
+    }
+
+    #[test]
+    fn captured_output() {
+        let output = SharedBuffer::default();
+        Interpreter::with_output(Box::new(output.clone()))
+            .interpret(&parse_statements(
+                "print \"a\" + \"b\"; var n; print n; print 1.5 * 2;",
+            ))
+            .unwrap();
+
+        assert_eq!(*output.0.borrow(), b"ab\nnil\n3\n");
+    }
+
+    #[test]
+    fn error_halts_execution() {
+        let mut interpreter = Interpreter::new();
+        let err = interpreter
+            .interpret(&parse_statements("var a = 1; a = -nil; a = 3;"))
+            .unwrap_err();
+        assert_eq!(err.message, "Operand must be a number, got nil.");
+
+        let expr = Parser::new(Scanner::new("a").scan_tokens())
+            .parse_expression()
+            .unwrap();
+        assert_eq!(interpreter.evaluate(&expr), Ok(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn block_shadowing() {
+        assert_eq!(
+            run("var a = 1; { var a = 2; print a; } print a;"),
+            Ok("2\n1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn block_assigns_shadowed_name() {
+        assert_eq!(
+            run("var a = 1; var b = 1; { var a = 2; a = 3; b = a; } print a; print b;"),
+            Ok("1\n3\n".to_string())
+        );
+    }
+
+    #[test]
+    fn scope_restored_after_error() {
+        let mut interpreter = Interpreter::new();
+        let statements = parse_statements("var a = 1; { var a = 2; -nil; }");
+        assert!(interpreter.interpret(&statements).is_err());
+
+        let expr = Parser::new(Scanner::new("a").scan_tokens())
+            .parse_expression()
+            .unwrap();
+        assert_eq!(interpreter.evaluate(&expr), Ok(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn if_then() {
+        assert_eq!(
+            run("if (true) print 1; else print 2;"),
+            Ok("1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn if_else() {
+        assert_eq!(
+            run("if (nil) print 1; else print 2;"),
+            Ok("2\n".to_string())
+        );
+        assert_eq!(run("if (false) print 1;"), Ok("".to_string()));
+    }
+
+    #[test]
+    fn while_loop() {
+        assert_eq!(
+            run("var i = 0; while (i < 3) { print i; i = i + 1; }"),
+            Ok("0\n1\n2\n".to_string())
+        );
+    }
+
+    #[test]
+    fn error_in_loop_body() {
+        let err = run("var i = 0; while (true) { i = i + 1; if (i > 2) -nil; }").unwrap_err();
+        assert_eq!(err.message, "Operand must be a number, got nil.");
+    }
+
+    #[test]
+    fn for_loop() {
+        assert_eq!(
+            run("for (var i = 0; i < 3; i = i + 1) print i;"),
+            Ok("0\n1\n2\n".to_string())
+        );
+    }
+
+    #[test]
+    fn logical_short_circuit() {
+        assert_eq!(
+            run("var a = 0; false and (a = 1); true or (a = 2); print a;"),
+            Ok("0\n".to_string())
+        );
+        assert_eq!(
+            run("var a = 0; true and (a = 1); false or (a = 2); print a;"),
+            Ok("2\n".to_string())
+        );
+    }
+
+    #[test]
+    fn logical_returns_operand() {
+        assert_eq!(evaluate("nil or \"x\""), Ok(Value::Str("x".to_string())));
+        assert_eq!(evaluate("1 and 2"), Ok(Value::Number(2.0)));
+        assert_eq!(evaluate("nil and 2"), Ok(Value::Nil));
+        assert_eq!(evaluate("0 or 2"), Ok(Value::Number(0.0)));
+    }
+
+    #[test]
+    fn native_clock() {
+        assert!(matches!(evaluate("clock()"), Ok(Value::Number(_))));
+        assert_eq!(
+            evaluate("clock(1)").unwrap_err().message,
+            "Expected 0 arguments but got 1."
+        );
+    }
+
+    #[test]
+    fn native_len() {
+        assert_eq!(evaluate("len(\"hello\")"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate("len(\"héllo\")"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate("len([1, 2, 3])"), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate("len([])"), Ok(Value::Number(0.0)));
+
+        let err = evaluate("\nlen(1)").unwrap_err();
+        assert_eq!(
+            err.message,
+            "Can only take the length of strings and lists, got number."
+        );
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn native_substring() {
+        let substring = |source| evaluate(source).map(|value| value.to_string());
+
+        assert_eq!(
+            substring("substring(\"hello\", 1, 3)"),
+            Ok("el".to_string())
+        );
+        assert_eq!(
+            substring("substring(\"hello\", 0, 5)"),
+            Ok("hello".to_string())
+        );
+        assert_eq!(substring("substring(\"hello\", 5, 5)"), Ok("".to_string()));
+        assert_eq!(substring("substring(\"héllo\", 1, 2)"), Ok("é".to_string()));
+
+        assert_eq!(
+            evaluate("substring(\"hello\", 2, 6)").unwrap_err().message,
+            "Substring range 2..6 is out of bounds for length 5."
+        );
+        assert_eq!(
+            evaluate("substring(\"hello\", 3, 2)").unwrap_err().message,
+            "Substring range 3..2 is out of bounds for length 5."
+        );
+        assert_eq!(
+            evaluate("substring(\"hello\", -1, 2)").unwrap_err().message,
+            "Index must be a non-negative integer, got -1."
+        );
+        assert_eq!(
+            evaluate("substring(\"hello\", 0, nil)")
+                .unwrap_err()
+                .message,
+            "Index must be a number, got nil."
+        );
+        assert_eq!(
+            evaluate("substring(1, 0, 1)").unwrap_err().message,
+            "substring() expects a string, got number."
+        );
+    }
+
+    #[test]
+    fn native_upper_and_lower() {
+        assert_eq!(
+            evaluate("upper(\"Hello, world\")"),
+            Ok(Value::Str("HELLO, WORLD".to_string()))
+        );
+        assert_eq!(
+            evaluate("lower(\"Hello, WORLD\")"),
+            Ok(Value::Str("hello, world".to_string()))
+        );
+        assert_eq!(
+            evaluate("upper(true)").unwrap_err().message,
+            "upper() expects a string, got bool."
+        );
+        assert_eq!(
+            evaluate("lower([])").unwrap_err().message,
+            "lower() expects a string, got list."
+        );
+    }
+
+    #[test]
+    fn native_input() {
+        let output = SharedBuffer::new();
+        let mut interpreter = Interpreter::with_output(Box::new(output.clone()));
+        interpreter.set_input(Box::new(io::Cursor::new("Ada\r\nLovelace")));
+
+        let source = "print \"Hello, \" + input() + \"!\"; print input(); print input();";
+        interpreter.interpret(&parse_statements(source)).unwrap();
+
+        assert_eq!(output.contents(), "Hello, Ada!\nLovelace\nnil\n");
+    }
+
+    #[test]
+    fn native_type() {
+        let source = "
+            fun f() {}
+            class A {}
+            print type(1);
+            print type(\"a\");
+            print type(true);
+            print type(nil);
+            print type(f);
+            print type(clock);
+            print type(fun () {});
+            print type(A);
+            print type(A());
+            print type([]);
+        ";
+        assert_eq!(
+            run(source),
+            Ok(
+                "number\nstring\nbool\nnil\nfunction\nfunction\nfunction\nclass\ninstance\nlist\n"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn native_assert() {
+        assert_eq!(
+            run("assert(true); assert(1 < 2, \"unused\"); print \"ok\";"),
+            Ok("ok\n".to_string())
+        );
+
+        let err = run("print 1;\nassert(1 > 2);").unwrap_err();
+        assert_eq!(err.message, "Assertion failed");
+        assert_eq!(err.line, 2);
+
+        let err = run("assert(nil, \"expected a value\");").unwrap_err();
+        assert_eq!(err.message, "expected a value");
+
+        assert_eq!(
+            run("assert();").unwrap_err().message,
+            "Expected 1 to 2 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn call_non_callable() {
+        assert_eq!(
+            evaluate("\"clock\"()").unwrap_err().message,
+            "Can only call functions and classes."
+        );
+    }
+
+    #[test]
+    fn function_call() {
+        assert_eq!(
+            run("fun add(a, b) { print a + b; } add(1, 2); print add;"),
+            Ok("3\n<fn add>\n".to_string())
+        );
+    }
+
+    #[test]
+    fn closure_counter() {
+        let source = "
+            fun makeCounter() {
+                var count = 0;
+                fun counter() {
+                    count = count + 1;
+                    print count;
+                }
+                counter();
+                counter();
+                print count;
+            }
+            makeCounter();
+        ";
+        assert_eq!(run(source), Ok("1\n2\n2\n".to_string()));
+    }
+
+    #[test]
+    fn return_value() {
+        assert_eq!(
+            run("fun add(a, b) { return a + b; } print add(1, 2) * 2;"),
+            Ok("6\n".to_string())
+        );
+    }
+
+    #[test]
+    fn early_return_from_loop() {
+        let source = "
+            fun find() {
+                var i = 0;
+                while (true) {
+                    { if (i == 3) return i; }
+                    i = i + 1;
+                }
+                print \"unreachable\";
+            }
+            print find();
+        ";
+        assert_eq!(run(source), Ok("3\n".to_string()));
+    }
+
+    #[test]
+    fn implicit_return_nil() {
+        assert_eq!(
+            run("fun f() { return; } fun g() {} print f(); print g();"),
+            Ok("nil\nnil\n".to_string())
+        );
+    }
+
+    #[test]
+    fn closure_across_calls() {
+        let source = "
+            fun makeCounter() {
+                var count = 0;
+                fun counter() {
+                    count = count + 1;
+                    return count;
+                }
+                return counter;
+            }
+            var counter = makeCounter();
+            counter();
+            print counter();
+        ";
+        assert_eq!(run(source), Ok("2\n".to_string()));
+    }
+
+    #[test]
+    fn too_few_arguments() {
+        let err = run("fun add(a, b) { return a + b; }\nadd(1);").unwrap_err();
+        assert_eq!(err.message, "Expected 2 arguments but got 1.");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn too_many_arguments() {
+        let err = run("fun add(a, b) { return a + b; }\n\nadd(1, 2, 3);").unwrap_err();
+        assert_eq!(err.message, "Expected 2 arguments but got 3.");
+        assert_eq!(err.line, 3);
+    }
+
+    #[test]
+    fn closure_keeps_resolved_binding() {
+        let source = "
+            var a = \"global\";
+            {
+                fun showA() {
+                    print a;
+                }
+
+                showA();
+                var a = \"block\";
+                showA();
+                print a;
+            }
+        ";
+        assert_eq!(run(source), Ok("global\nglobal\nblock\n".to_string()));
+    }
+
+    #[test]
+    fn class_instance() {
+        assert_eq!(
+            run("class Cake {} var c = Cake(); print Cake; print c;"),
+            Ok("Cake\nCake instance\n".to_string())
+        );
+    }
+
+    #[test]
+    fn distinct_instances() {
+        assert_eq!(
+            run("class Cake {} var a = Cake(); var b = Cake(); print a == b; print a == a;"),
+            Ok("false\ntrue\n".to_string())
+        );
+    }
+
+    #[test]
+    fn instance_fields() {
+        assert_eq!(
+            run("class Cake {} var c = Cake(); c.flavor = \"lemon\"; print c.flavor = c.flavor + \"!\"; print c.flavor;"),
+            Ok("lemon!\nlemon!\n".to_string())
+        );
+    }
+
+    #[test]
+    fn undefined_property() {
+        let err = run("class Cake {} var c = Cake();\nprint c.flavor;").unwrap_err();
+        assert_eq!(err.message, "Undefined property 'flavor'.");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn property_on_non_instance() {
+        assert_eq!(
+            run("var n = 1; print n.field;").unwrap_err().message,
+            "Only instances have properties."
+        );
+        assert_eq!(
+            run("var n = 1; n.field = 2;").unwrap_err().message,
+            "Only instances have fields."
+        );
+    }
+
+    #[test]
+    fn method_reads_this() {
+        let source = "
+            class Person {
+                greet() {
+                    print \"Hi \" + this.name;
+                }
+            }
+            var p = Person();
+            p.name = \"Ann\";
+            p.greet();
+            var greet = p.greet;
+            p.name = \"Bob\";
+            greet();
+            print greet;
+        ";
+        assert_eq!(run(source), Ok("Hi Ann\nHi Bob\n<fn greet>\n".to_string()));
+    }
+
+    #[test]
+    fn class_initializer() {
+        let source = "
+            class Cake {
+                init(flavor) {
+                    this.flavor = flavor;
+                    return;
+                }
+            }
+            var c = Cake(\"lemon\");
+            print c.flavor;
+            print c.init(\"lime\") == c;
+            print c.flavor;
+        ";
+        assert_eq!(run(source), Ok("lemon\ntrue\nlime\n".to_string()));
+    }
+
+    #[test]
+    fn initializer_arity() {
+        assert_eq!(
+            run("class Cake { init(flavor) {} } Cake();")
+                .unwrap_err()
+                .message,
+            "Expected 1 arguments but got 0."
+        );
+    }
+
+    #[test]
+    fn super_call() {
+        let source = "
+            class A {
+                method() {
+                    return \"A \" + this.name;
+                }
+                inherited() {
+                    return \"inherited\";
+                }
+            }
+            class B < A {
+                method() {
+                    return \"B then \" + super.method();
+                }
+            }
+            var b = B();
+            b.name = \"b\";
+            print b.method();
+            print b.inherited();
+        ";
+        assert_eq!(run(source), Ok("B then A b\ninherited\n".to_string()));
+    }
+
+    #[test]
+    fn superclass_not_class() {
+        let err = run("var A = 1;\nclass B < A {}").unwrap_err();
+        assert_eq!(err.message, "Superclass must be a class.");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn list_literal() {
+        assert_eq!(
+            run("var a = [1, \"two\", [nil]]; print a;"),
+            Ok("[1, two, [nil]]\n".to_string())
+        );
+    }
+
+    #[test]
+    fn read_and_write_elements() {
+        assert_eq!(
+            run("var a = [1, 2, 3]; a[1] = a[0] + a[2]; print a[1]; print a;"),
+            Ok("4\n[1, 4, 3]\n".to_string())
+        );
+    }
+
+    #[test]
+    fn lists_are_shared() {
+        assert_eq!(
+            run("var a = [1]; var b = a; b[0] = 2; print a; print a == b; print [] == [];"),
+            Ok("[2]\ntrue\nfalse\n".to_string())
+        );
+    }
+
+    #[test]
+    fn print_self_referencing_list() {
+        assert_eq!(
+            run("var a = [1]; a[0] = a; print a; print [a, 2];"),
+            Ok("[[...]]\n[[[...]], 2]\n".to_string())
+        );
+    }
+
+    #[test]
+    fn index_out_of_bounds() {
+        let err = run("var a = [1, 2];\nprint a[2];").unwrap_err();
+        assert_eq!(err.message, "List index 2 out of bounds for length 2.");
+        assert_eq!(err.line, 2);
+
+        let err = run("var a = [];\na[-1] = 1;").unwrap_err();
+        assert_eq!(err.message, "List index -1 out of bounds for length 0.");
+    }
+
+    #[test]
+    fn invalid_index() {
+        let err = run("[1][0.5];").unwrap_err();
+        assert_eq!(err.message, "List index must be an integer, got 0.5.");
+
+        let err = run("[1][\"0\"];").unwrap_err();
+        assert_eq!(err.message, "List index must be a number, got string.");
+
+        let err = run("\"abc\"[0];").unwrap_err();
+        assert_eq!(err.message, "Only lists can be indexed, got string.");
+    }
+
+    #[test]
+    fn break_exits_loop() {
+        assert_eq!(
+            run("var i = 0; while (true) { if (i == 2) break; print i; i = i + 1; } print \"done\";"),
+            Ok("0\n1\ndone\n".to_string())
+        );
+    }
+
+    #[test]
+    fn break_exits_innermost_loop() {
+        let source = "for (var i = 0; i < 2; i = i + 1) for (;;) { print i; break; }";
+        assert_eq!(run(source), Ok("0\n1\n".to_string()));
+    }
+
+    #[test]
+    fn continue_skips_iteration() {
+        assert_eq!(
+            run("for (var i = 0; i < 4; i = i + 1) { if (i == 1) continue; print i; }"),
+            Ok("0\n2\n3\n".to_string())
+        );
+
+        let source = "var i = 0; while (i < 3) { i = i + 1; if (i == 2) continue; print i; }";
+        assert_eq!(run(source), Ok("1\n3\n".to_string()));
+    }
+
+    #[test]
+    fn unbounded_recursion() {
+        let err = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| run("fun f(n) {\n  return f(n + 1);\n}\nf(0);").unwrap_err())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(err.message, "Stack overflow");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn configured_call_depth() {
+        let source = "fun f(n) { if (n > 0) f(n - 1); } f(5);";
+
+        let mut interpreter = Interpreter::with_output(Box::new(io::sink()));
+        interpreter.set_max_call_depth(6);
+        assert!(interpreter.interpret(&parse_statements(source)).is_ok());
+
+        interpreter.set_max_call_depth(5);
+        let err = interpreter
+            .interpret(&parse_statements(source))
+            .unwrap_err();
+        assert_eq!(err.message, "Stack overflow");
+    }
+}
@@ -0,0 +1,221 @@
+//! Tree-walking interpreter for Lox. [`run`] takes source text all the way through, the stages
+//! are also usable on their own: [`Scanner`] → [`Parser`] → [`Resolver`] → [`Interpreter`]
+
+pub mod ast_printer;
+pub mod callable;
+pub mod class;
+pub mod diagnostics;
+pub mod environment;
+pub mod expr;
+pub mod function;
+pub mod interner;
+pub mod interpreter;
+pub mod parser;
+pub mod resolver;
+pub mod scanner;
+pub mod stmt;
+pub mod value;
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::mpsc;
+
+use diagnostics::Diagnostic;
+pub use interpreter::{Interpreter, RuntimeError};
+pub use parser::{ParseError, Parser};
+pub use resolver::{ResolveError, Resolver};
+use scanner::Token;
+pub use scanner::{ScanError, Scanner};
+use stmt::Stmt;
+
+/// Any error from running Lox source, by the stage that found it
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoxError {
+    Scan(ScanError),
+    Parse(ParseError),
+    Resolve(ResolveError),
+    Runtime(RuntimeError),
+    /// Writing what the script printed failed, the script stopped at that `print`
+    Io(IoError),
+}
+
+/// An I/O failure while running a script, which has no place in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct IoError {
+    pub kind: io::ErrorKind,
+    pub message: String,
+}
+
+impl IoError {
+    fn new(err: &io::Error) -> Self {
+        IoError {
+            kind: err.kind(),
+            message: format!("Failed to write output: {}.", err),
+        }
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {}", self.message)
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxError::Scan(err) => err.fmt(f),
+            LoxError::Parse(err) => err.fmt(f),
+            LoxError::Resolve(err) => err.fmt(f),
+            LoxError::Runtime(err) => err.fmt(f),
+            LoxError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Diagnostic for LoxError {
+    fn message(&self) -> &str {
+        match self {
+            LoxError::Scan(err) => err.message(),
+            LoxError::Parse(err) => err.message(),
+            LoxError::Resolve(err) => err.message(),
+            LoxError::Runtime(err) => err.message(),
+            LoxError::Io(err) => err.message(),
+        }
+    }
+
+    fn location(&self) -> (usize, usize) {
+        match self {
+            LoxError::Scan(err) => err.location(),
+            LoxError::Parse(err) => err.location(),
+            LoxError::Resolve(err) => err.location(),
+            LoxError::Runtime(err) => err.location(),
+            LoxError::Io(err) => err.location(),
+        }
+    }
+}
+
+/// Runs `source` as a script, writing what it prints to `out` as it goes. Errors from the first
+/// stage that fails are returned, so a runtime error or a failure to write to `out` is the only one
+/// that comes after output. The script runs on its own thread with a stack of
+/// [`interpreter::STACK_SIZE`], so deep recursion reaches the call depth limit instead of
+/// overflowing the caller's stack
+pub fn run(source: &str, out: &mut dyn Write) -> Result<(), Vec<LoxError>> {
+    let (chunks, received) = mpsc::sync_channel(0);
+    let (outcomes, replies) = mpsc::channel();
+    let mut failure = None;
+
+    let result = interpreter::with_stack_alongside(
+        move || {
+            execute(
+                source,
+                Forward {
+                    chunks,
+                    outcomes: replies,
+                },
+            )
+        },
+        || {
+            // Ends once the interpreter's thread is done and drops its `Forward`
+            for chunk in received {
+                let outcome = out.write_all(&chunk);
+                if let Err(err) = &outcome {
+                    failure = Some(IoError::new(err));
+                }
+                let _ = outcomes.send(outcome);
+            }
+        },
+    );
+    match failure {
+        // The interpreter only saw the failed write as a runtime error without a position
+        Some(err) => Err(vec![LoxError::Io(err)]),
+        None => result,
+    }
+}
+
+/// The part of `run` on the interpreter's thread
+fn execute(source: &str, output: Forward) -> Result<(), Vec<LoxError>> {
+    let statements = prepare(source)?;
+    Interpreter::with_output(Box::new(output))
+        .interpret(&statements)
+        .map_err(|err| vec![LoxError::Runtime(err)])
+}
+
+/// Hands each write on the interpreter's thread over to the thread holding `run`'s `out`, waiting
+/// until it's written so nothing piles up in between
+struct Forward {
+    chunks: mpsc::SyncSender<Vec<u8>>,
+    outcomes: mpsc::Receiver<io::Result<()>>,
+}
+
+impl Write for Forward {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let gone = || io::Error::from(io::ErrorKind::BrokenPipe);
+        self.chunks.send(buf.to_vec()).map_err(|_| gone())?;
+        self.outcomes.recv().map_err(|_| gone())??;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Scans `source`, returning every scan error if there are any
+pub fn scan(source: &str) -> Result<Vec<Token>, Vec<LoxError>> {
+    Scanner::new(source)
+        .scan()
+        .map_err(|errors| errors.into_iter().map(LoxError::Scan).collect())
+}
+
+/// Scans and parses `source`, stopping at the first stage that fails
+pub fn parse(source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+    Parser::new(scan(source)?)
+        .parse()
+        .map_err(|errors| errors.into_iter().map(LoxError::Parse).collect())
+}
+
+/// Scans, parses and resolves `source`, stopping at the first stage that fails
+pub fn prepare(source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+    let statements = parse(source)?;
+    Resolver::new().resolve(&statements).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(LoxError::Resolve)
+            .collect::<Vec<_>>()
+    })?;
+    Ok(statements)
+}
+
+/// Runs `source` as a script and returns the lines it printed. Unlike `run`, parsing goes on past
+/// scan errors so every scan, parse and resolve error is returned together. Runs on its own thread
+/// like `run`
+pub fn interpret_source(source: &str) -> Result<Vec<String>, Vec<LoxError>> {
+    interpreter::with_stack(|| collect_output(source))
+}
+
+fn collect_output(source: &str) -> Result<Vec<String>, Vec<LoxError>> {
+    let (tokens, scan_errors) = Scanner::new(source).scan_all();
+    let mut errors: Vec<LoxError> = scan_errors.into_iter().map(LoxError::Scan).collect();
+
+    match Parser::new(tokens).parse() {
+        Ok(statements) => {
+            if let Err(resolve_errors) = Resolver::new().resolve(&statements) {
+                errors.extend(resolve_errors.into_iter().map(LoxError::Resolve));
+            }
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+
+            let printed = interpreter::SharedBuffer::new();
+            Interpreter::with_output(Box::new(printed.clone()))
+                .interpret(&statements)
+                .map_err(|err| vec![LoxError::Runtime(err)])?;
+            Ok(printed.contents().lines().map(String::from).collect())
+        }
+        Err(parse_errors) => {
+            errors.extend(parse_errors.into_iter().map(LoxError::Parse));
+            Err(errors)
+        }
+    }
+}
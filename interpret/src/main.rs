@@ -1,42 +1,477 @@
-mod scanner;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs, thread};
 
-use std::{env, fs, io};
+use interpret::diagnostics::Diagnostic;
+use interpret::expr::Expr;
+use interpret::scanner::Token;
+use interpret::stmt::Stmt;
+use interpret::{ast_printer, interpreter, Interpreter, LoxError, Parser, Resolver};
 
-use scanner::Scanner;
+/// Why running a script failed, following the sysexits codes jlox uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Failure {
+    /// Scanning, parsing or resolving failed, nothing was run
+    Compile = 65,
+    Runtime = 70,
+}
+
+/// Scans, parses, resolves and interprets `code`, reporting errors to stderr
+fn execute(code: &str, interpreter: &mut Interpreter) -> Result<(), Failure> {
+    let statements = interpret::prepare(code).map_err(|errors| {
+        report(code, &errors);
+        Failure::Compile
+    })?;
+    interpreter.interpret(&statements).map_err(|err| {
+        report(code, &[err]);
+        Failure::Runtime
+    })
+}
 
-fn run(code: String) -> io::Result<()> {
-    let scanner = Scanner::new(code);
-    let tokens = scanner.scan_tokens();
+/// What a REPL entry read so far turned out to be
+enum Entry {
+    Complete(Vec<Stmt>),
+    /// Parsing ran out of input, more lines may complete it
+    Incomplete,
+    /// Errors have already been reported
+    Invalid,
+}
+
+/// Scans, parses and resolves a REPL entry, reporting errors to stderr. The `;` after a lone
+/// expression is optional
+fn parse_entry(source: &str) -> Entry {
+    let tokens = match interpret::scan(source) {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            report(source, &errors);
+            return Entry::Invalid;
+        }
+    };
+    let statements = match Parser::new(tokens.clone()).parse() {
+        Ok(statements) => statements,
+        Err(errors) => match bare_expression(tokens) {
+            Some(expr) => vec![Stmt::Expression(expr)],
+            None if errors.iter().all(|err| err.token.is_empty()) => return Entry::Incomplete,
+            None => {
+                report(source, &errors);
+                return Entry::Invalid;
+            }
+        },
+    };
+    match Resolver::new().resolve(&statements) {
+        Ok(()) => Entry::Complete(statements),
+        Err(errors) => {
+            report(source, &errors);
+            Entry::Invalid
+        }
+    }
+}
 
-    for tok in tokens {
-        println!("{:?}", tok);
+/// Runs a REPL entry, an entry holding a single expression has its value echoed to `output`
+fn execute_entry(
+    source: &str,
+    statements: &[Stmt],
+    interpreter: &mut Interpreter,
+    output: &mut dyn Write,
+) -> io::Result<()> {
+    // Functions from earlier entries report lines of their own entry, but are quoted against this
+    // one
+    match statements {
+        [Stmt::Expression(expr)] => match interpreter.evaluate(expr) {
+            Ok(value) => writeln!(output, "{}", value)?,
+            Err(err) => report(source, &[err]),
+        },
+        _ => {
+            if let Err(err) = interpreter.interpret(statements) {
+                report(source, &[err]);
+            }
+        }
     }
     Ok(())
 }
 
+/// Parses `tokens` as one expression without a `;` after it
+fn bare_expression(tokens: Vec<Token>) -> Option<Expr> {
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse_expression().ok()?;
+    if parser.is_at_end() {
+        Some(expr)
+    } else {
+        None
+    }
+}
+
+/// Prints `errors` to stderr, quoting the lines of `source` they point at
+fn report(source: &str, errors: &[impl Diagnostic]) {
+    for error in errors {
+        eprintln!("{}", error.render(source));
+    }
+}
+
 fn start_prompt() -> io::Result<()> {
-    let mut line = String::new();
+    prompt(
+        // Not a stdin lock, `input()` needs to read from it too
+        &mut interpreter::StdinReader::default(),
+        &mut io::stdout(),
+        &mut Interpreter::new(),
+    )
+}
+
+/// Runs each entry read from `input` until it ends, which is how Ctrl-D ends the REPL. Entries
+/// continue over several lines until they parse. The same interpreter runs every entry so
+/// definitions carry over
+fn prompt(
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    interpreter: &mut Interpreter,
+) -> io::Result<()> {
+    let mut entry = String::new();
     loop {
-        print!("> ");
-        io::stdin().read_line(&mut line)?;
+        write!(output, "{}", if entry.is_empty() { "> " } else { "... " })?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            if entry.is_empty() {
+                return Ok(());
+            }
+            // Ctrl-D in the middle of an entry only abandons it
+            entry.clear();
+            continue;
+        }
+
+        entry.push_str(&line);
+        match parse_entry(&entry) {
+            Entry::Complete(statements) => execute_entry(&entry, &statements, interpreter, output)?,
+            Entry::Incomplete => continue,
+            Entry::Invalid => {}
+        }
+        entry.clear();
     }
 }
 
-fn run_file(filename: &String) -> io::Result<()> {
+fn run_file(filename: &str) -> io::Result<Result<(), Failure>> {
+    let file = fs::read_to_string(filename)?;
+
+    Ok(execute(&file, &mut Interpreter::new()))
+}
+
+/// Takes a script through `stage` and prints the result with `write`, without running it
+fn dump<T>(
+    filename: &str,
+    output: &mut dyn Write,
+    stage: fn(&str) -> Result<T, Vec<LoxError>>,
+    write: impl FnOnce(&str, T, &mut dyn Write) -> io::Result<()>,
+) -> io::Result<Result<(), Failure>> {
     let file = fs::read_to_string(filename)?;
 
-    run(file)
+    match stage(&file) {
+        Ok(result) => {
+            write(&file, result, output)?;
+            Ok(Ok(()))
+        }
+        Err(errors) => {
+            report(&file, &errors);
+            Ok(Err(Failure::Compile))
+        }
+    }
+}
+
+/// `--tokens`, one token per line
+fn write_tokens(source: &str, tokens: Vec<Token>, output: &mut dyn Write) -> io::Result<()> {
+    for token in tokens {
+        writeln!(output, "{}", token.with_source(source))?;
+    }
+    Ok(())
+}
+
+/// `--tokens-json`, a JSON array of `{kind, lexeme, line, column}` objects
+#[cfg(feature = "serde")]
+fn write_tokens_json(source: &str, tokens: Vec<Token>, output: &mut dyn Write) -> io::Result<()> {
+    let tokens: Vec<_> = tokens
+        .iter()
+        .map(|token| token.with_source(source))
+        .collect();
+    serde_json::to_writer(&mut *output, &tokens)?;
+    writeln!(output)
+}
+
+/// `--ast`, one statement per line
+fn write_ast(_: &str, statements: Vec<Stmt>, output: &mut dyn Write) -> io::Result<()> {
+    for statement in &statements {
+        writeln!(output, "{}", ast_printer::print_stmt(statement))?;
+    }
+    Ok(())
+}
+
+/// `--ast-json`, a JSON array in the shape documented on `Stmt`
+#[cfg(feature = "serde")]
+fn write_ast_json(_: &str, statements: Vec<Stmt>, output: &mut dyn Write) -> io::Result<()> {
+    serde_json::to_writer(&mut *output, &statements)?;
+    writeln!(output)
+}
+
+/// Runs everything piped to stdin as one script
+fn run_stdin() -> io::Result<Result<(), Failure>> {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+
+    Ok(execute(&source, &mut Interpreter::new()))
+}
+
+fn exit_code(result: Result<(), Failure>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => ExitCode::from(failure as u8),
+    }
+}
+
+/// What the command line asks for
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    /// No script was given, stdin is run if it is piped or else the REPL starts
+    Prompt,
+    RunFile(&'a str),
+    /// `--tokens`, only scans the script
+    Tokens(&'a str),
+    /// `--tokens-json`, only scans the script
+    #[cfg(feature = "serde")]
+    TokensJson(&'a str),
+    /// `--ast`, only scans and parses the script
+    Ast(&'a str),
+    /// `--ast-json`, only scans and parses the script
+    #[cfg(feature = "serde")]
+    AstJson(&'a str),
+    Usage,
+}
+
+/// `args` includes the program name first, as given by `env::args`
+fn parse_args(args: &[String]) -> Command<'_> {
+    match args {
+        [_] => Command::Prompt,
+        [_, flag, script] if flag == "--tokens" => Command::Tokens(script),
+        [_, flag, script] if flag == "--ast" => Command::Ast(script),
+        #[cfg(feature = "serde")]
+        [_, flag, script] if flag == "--tokens-json" => Command::TokensJson(script),
+        #[cfg(feature = "serde")]
+        [_, flag, script] if flag == "--ast-json" => Command::AstJson(script),
+        [_, script] if !script.starts_with("--") => Command::RunFile(script),
+        _ => Command::Usage,
+    }
 }
 
-fn main() -> io::Result<()> {
+/// The JSON dumps are only offered when built with the `serde` feature
+#[cfg(feature = "serde")]
+const USAGE: &str = "Usage: rlox [--tokens | --tokens-json | --ast | --ast-json] [script]";
+#[cfg(not(feature = "serde"))]
+const USAGE: &str = "Usage: rlox [--tokens | --ast] [script]";
+
+/// Exit code for bad command line usage
+const EX_USAGE: u8 = 64;
+
+fn main() -> ExitCode {
+    // Deeply recursive Lox programs need more stack than the main thread has
+    let status = thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(start)
+        .and_then(|interpreter| interpreter.join().expect("interpreter thread panicked"));
+
+    match status {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn start() -> io::Result<ExitCode> {
     let args: Vec<String> = env::args().collect();
 
-    match args.len() {
-        0 => start_prompt(),
-        1 => run_file(&args[0]),
-        _ => {
-            println!("Usage: rlox [script]");
-            Ok(())
+    match parse_args(&args) {
+        Command::Prompt if io::stdin().is_terminal() => start_prompt().map(|()| ExitCode::SUCCESS),
+        Command::Prompt => run_stdin().map(exit_code),
+        Command::RunFile(script) => run_file(script).map(exit_code),
+        Command::Tokens(script) => {
+            dump(script, &mut io::stdout(), interpret::scan, write_tokens).map(exit_code)
+        }
+        #[cfg(feature = "serde")]
+        Command::TokensJson(script) => dump(
+            script,
+            &mut io::stdout(),
+            interpret::scan,
+            write_tokens_json,
+        )
+        .map(exit_code),
+        Command::Ast(script) => {
+            dump(script, &mut io::stdout(), interpret::parse, write_ast).map(exit_code)
+        }
+        #[cfg(feature = "serde")]
+        Command::AstJson(script) => {
+            dump(script, &mut io::stdout(), interpret::parse, write_ast_json).map(exit_code)
         }
+        Command::Usage => {
+            eprintln!("{}", USAGE);
+            Ok(ExitCode::from(EX_USAGE))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    use interpret::interpreter::SharedBuffer;
+
+    #[test]
+    fn prompt_ends_at_eof() {
+        let mut input = io::Cursor::new("1 + 2;\nprint 3;\n");
+        let mut output = Vec::new();
+
+        prompt(
+            &mut input,
+            &mut output,
+            &mut Interpreter::with_output(Box::new(io::sink())),
+        )
+        .unwrap();
+
+        assert_eq!(output, b"> 3\n> > \n");
+    }
+
+    #[test]
+    fn prompt_keeps_environment() {
+        let mut input = io::Cursor::new("var x = 1;\nprint x + 1;\n");
+        let printed = SharedBuffer::new();
+
+        prompt(
+            &mut input,
+            &mut Vec::new(),
+            &mut Interpreter::with_output(Box::new(printed.clone())),
+        )
+        .unwrap();
+
+        assert_eq!(printed.contents(), "2\n");
+    }
+
+    #[test]
+    fn prompt_continues_after_error() {
+        let mut input = io::Cursor::new("print y;\nprint 1;\n");
+        let printed = SharedBuffer::new();
+
+        prompt(
+            &mut input,
+            &mut Vec::new(),
+            &mut Interpreter::with_output(Box::new(printed.clone())),
+        )
+        .unwrap();
+
+        assert_eq!(printed.contents(), "1\n");
+    }
+
+    #[test]
+    fn prompt_reports_errors_before_eof() {
+        let mut input = io::Cursor::new("print ); {\nprint 1;\n");
+        let printed = SharedBuffer::new();
+
+        prompt(
+            &mut input,
+            &mut Vec::new(),
+            &mut Interpreter::with_output(Box::new(printed.clone())),
+        )
+        .unwrap();
+
+        assert_eq!(printed.contents(), "1\n");
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn script_argument() {
+        assert_eq!(
+            parse_args(&args(&["rlox", "script.lox"])),
+            Command::RunFile("script.lox")
+        );
+    }
+
+    #[test]
+    fn no_arguments() {
+        assert_eq!(parse_args(&args(&["rlox"])), Command::Prompt);
+    }
+
+    #[test]
+    fn tokens_flag() {
+        assert_eq!(
+            parse_args(&args(&["rlox", "--tokens", "a.lox"])),
+            Command::Tokens("a.lox")
+        );
+        assert_eq!(
+            parse_args(&args(&["rlox", "--ast", "a.lox"])),
+            Command::Ast("a.lox")
+        );
+        assert_eq!(parse_args(&args(&["rlox", "--tokens"])), Command::Usage);
+        assert_eq!(
+            parse_args(&args(&["rlox", "--bogus", "a.lox"])),
+            Command::Usage
+        );
+    }
+
+    #[test]
+    fn too_many_arguments() {
+        assert_eq!(
+            parse_args(&args(&["rlox", "a.lox", "b.lox"])),
+            Command::Usage
+        );
+    }
+
+    #[test]
+    fn prompt_echoes_expressions() {
+        let mut input = io::Cursor::new("var a = 1;\na + 2\na = 5;\nprint a; print a;\n");
+        let mut output = Vec::new();
+        let printed = SharedBuffer::new();
+
+        prompt(
+            &mut input,
+            &mut output,
+            &mut Interpreter::with_output(Box::new(printed.clone())),
+        )
+        .unwrap();
+
+        assert_eq!(output, b"> > 3\n> 5\n> > \n");
+        assert_eq!(printed.contents(), "5\n5\n");
+    }
+
+    #[test]
+    fn prompt_continues_entry() {
+        let mut input = io::Cursor::new("fun f() {\n  return 1;\n}\nprint f();\n");
+        let mut output = Vec::new();
+        let printed = SharedBuffer::new();
+
+        prompt(
+            &mut input,
+            &mut output,
+            &mut Interpreter::with_output(Box::new(printed.clone())),
+        )
+        .unwrap();
+
+        assert_eq!(output, b"> ... ... > > \n");
+        assert_eq!(printed.contents(), "1\n");
+    }
+
+    #[test]
+    fn prompt_abandons_entry_at_eof() {
+        let mut input = io::Cursor::new("fun f() {\n");
+        let mut output = Vec::new();
+
+        prompt(
+            &mut input,
+            &mut output,
+            &mut Interpreter::with_output(Box::new(io::sink())),
+        )
+        .unwrap();
+
+        assert_eq!(output, b"> ... \n> \n");
     }
 }
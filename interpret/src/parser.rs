@@ -0,0 +1,1547 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::expr::{Expr, LiteralValue};
+use crate::scanner::{Token, TokenType};
+use crate::stmt::Stmt;
+
+/// Most arguments a call can have, the limit keeps the door open for a bytecode implementation
+const MAX_ARGS: usize = 255;
+
+/// Parameters and body of a function, as shared with the functions made from it
+type FunctionParts = (Rc<[Token]>, Rc<Vec<Stmt>>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// What the parser expected to find
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    /// Lexeme of the token that was found instead, empty at the end of input
+    pub token: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.token.is_empty() {
+            write!(f, "[line {}] Error at end: {}", self.line, self.message)
+        } else {
+            write!(
+                f,
+                "[line {}] Error at '{}': {}",
+                self.line, self.token, self.message
+            )
+        }
+    }
+}
+
+/// Recursive descent parser over a scanned token stream, which is expected to end with
+/// `TokenType::Eof`
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser { tokens, current: 0 }
+    }
+
+    /// Parses declarations until the end of input. On a syntax error the parser skips to the next
+    /// statement boundary and carries on, so every error gets reported at once.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = vec![];
+        let mut errors = vec![];
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    errors.push(error);
+                    self.synchronize();
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.check(&TokenType::Fun) && !self.check_next(&TokenType::LeftParen) {
+            // `fun (` starts an anonymous function, which is parsed as an expression statement
+            self.advance();
+            self.function("function")
+        } else if self.match_any(&[TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume_identifier("Expect class name.")?.clone();
+
+        let superclass = if self.match_any(&[TokenType::Less]) {
+            let name = self.consume_identifier("Expect superclass name.")?.clone();
+            Some(Expr::Variable {
+                name,
+                depth: Cell::new(None),
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
+    /// Parses a named function after its introducing keyword, `kind` is only used in error
+    /// messages
+    fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume_identifier(&format!("Expect {} name.", kind))?
+            .clone();
+
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+        let (params, body) = self.function_body(kind)?;
+
+        Ok(Stmt::Function { name, params, body })
+    }
+
+    /// Parses the parameters and body of a function, expects the `(` to already be consumed
+    fn function_body(&mut self, kind: &str) -> Result<FunctionParts, ParseError> {
+        let mut params = vec![];
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGS {
+                    return Err(Parser::error(
+                        self.peek(),
+                        &format!("Can't have more than {} parameters.", MAX_ARGS),
+                    ));
+                }
+                params.push(self.consume_identifier("Expect parameter name.")?.clone());
+
+                if !self.match_any(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.block()?;
+
+        Ok((params.into(), Rc::new(body)))
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume_identifier("Expect variable name.")?.clone();
+
+        let initializer = if self.match_any(&[TokenType::Equal]) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(Stmt::Var { name, initializer })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[TokenType::For]) {
+            self.for_statement()
+        } else if self.match_any(&[TokenType::If]) {
+            self.if_statement()
+        } else if self.match_any(&[TokenType::Print]) {
+            self.print_statement()
+        } else if self.match_any(&[TokenType::Return]) {
+            self.return_statement()
+        } else if self.match_any(&[TokenType::Break]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Semicolon, "Expect ';' after 'break'.")?;
+            Ok(Stmt::Break { keyword })
+        } else if self.match_any(&[TokenType::Continue]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.")?;
+            Ok(Stmt::Continue { keyword })
+        } else if self.match_any(&[TokenType::While]) {
+            self.while_statement()
+        } else if self.match_any(&[TokenType::LeftBrace]) {
+            Ok(Stmt::Block(self.block()?))
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    /// Parses the declarations in a block, expects the `{` to already be consumed
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = vec![];
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    /// There's no dedicated for loop in the AST, it's desugared into the equivalent while loop:
+    /// `{ init; while (cond) body }` with the increment kept on the loop so `continue` still runs
+    /// it
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_any(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_any(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(&TokenType::Semicolon) {
+            Expr::Literal(LiteralValue::Bool(true))
+        } else {
+            self.parse_expression()?
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = Stmt::While {
+            condition,
+            body: Box::new(self.statement()?),
+            increment,
+        };
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    /// The `else` is claimed by the innermost `if`, which settles the dangling else ambiguity
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.parse_expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.match_any(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+
+        let value = if self.check(&TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.parse_expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While {
+            condition,
+            body,
+            increment: None,
+        })
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let value = self.parse_expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Print(value))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.parse_expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        self.assignment()
+    }
+
+    /// Assignment is right associative, and the left side is parsed as a normal expression before
+    /// checking that it's something that can be assigned to
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.ternary()?;
+
+        if self.match_any(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            return match expr {
+                Expr::Variable { name, .. } => Ok(Expr::Assign {
+                    name,
+                    value: Box::new(value),
+                    depth: Cell::new(None),
+                }),
+                Expr::Get { object, name } => Ok(Expr::Set {
+                    object,
+                    name,
+                    value: Box::new(value),
+                }),
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => Ok(Expr::SetIndex {
+                    object,
+                    bracket,
+                    index,
+                    value: Box::new(value),
+                }),
+                _ => Err(Parser::error(&equals, "Invalid assignment target.")),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// The else branch recurses so chains group to the right, `a ? b : c ? d : e` is
+    /// `a ? b : (c ? d : e)`
+    fn ternary(&mut self) -> Result<Expr, ParseError> {
+        let cond = self.or()?;
+
+        if !self.match_any(&[TokenType::Question]) {
+            return Ok(cond);
+        }
+
+        let then_expr = self.parse_expression()?;
+        self.consume(
+            TokenType::Colon,
+            "Expect ':' after then branch of conditional expression.",
+        )?;
+        let else_expr = self.ternary()?;
+
+        Ok(Expr::Ternary {
+            cond: Box::new(cond),
+            then_expr: Box::new(then_expr),
+            else_expr: Box::new(else_expr),
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+
+        while self.match_any(&[TokenType::Or]) {
+            let op = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.equality()?;
+
+        while self.match_any(&[TokenType::And]) {
+            let op = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.comparison()?;
+
+        while self.match_any(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+            let op = self.previous().clone();
+            let right = self.comparison()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.term()?;
+
+        while self.match_any(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ]) {
+            let op = self.previous().clone();
+            let right = self.term()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.factor()?;
+
+        while self.match_any(&[TokenType::Minus, TokenType::Plus]) {
+            let op = self.previous().clone();
+            let right = self.factor()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.match_any(&[TokenType::Slash, TokenType::Asterisk, TokenType::Percent]) {
+            let op = self.previous().clone();
+            let right = self.unary()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_any(&[TokenType::Bang, TokenType::Minus]) {
+            let op = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Unary {
+                op,
+                right: Box::new(right),
+            });
+        }
+
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_any(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_any(&[TokenType::Dot]) {
+                let name = self
+                    .consume_identifier("Expect property name after '.'.")?
+                    .clone();
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+            } else if self.match_any(&[TokenType::LeftBracket]) {
+                let index = self.parse_expression()?;
+                let bracket = self
+                    .consume(TokenType::RightBracket, "Expect ']' after index.")?
+                    .clone();
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses the arguments of a call, expects the `(` to already be consumed
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut args = vec![];
+
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if args.len() >= MAX_ARGS {
+                    return Err(Parser::error(
+                        self.peek(),
+                        &format!("Can't have more than {} arguments.", MAX_ARGS),
+                    ));
+                }
+                args.push(self.parse_expression()?);
+
+                if !self.match_any(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+
+        Ok(Expr::Call {
+            callee: Box::new(callee),
+            paren,
+            args,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let literal = match &self.peek().kind {
+            TokenType::False => LiteralValue::Bool(false),
+            TokenType::True => LiteralValue::Bool(true),
+            TokenType::Nil => LiteralValue::Nil,
+            TokenType::Number(value) => LiteralValue::Number(*value),
+            TokenType::String(value) => LiteralValue::String(value.clone()),
+            TokenType::Super => {
+                let keyword = self.advance().clone();
+                self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+                let method = self
+                    .consume_identifier("Expect superclass method name.")?
+                    .clone();
+                return Ok(Expr::Super {
+                    keyword,
+                    method,
+                    depth: Cell::new(None),
+                });
+            }
+            TokenType::This => {
+                let keyword = self.advance().clone();
+                return Ok(Expr::This {
+                    keyword,
+                    depth: Cell::new(None),
+                });
+            }
+            TokenType::Identifier(_) => {
+                let name = self.advance().clone();
+                return Ok(Expr::Variable {
+                    name,
+                    depth: Cell::new(None),
+                });
+            }
+            TokenType::Fun => {
+                let keyword = self.advance().clone();
+                self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+                let (params, body) = self.function_body("function")?;
+                return Ok(Expr::Function {
+                    keyword,
+                    params,
+                    body,
+                });
+            }
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut elements = vec![];
+
+                if !self.check(&TokenType::RightBracket) {
+                    loop {
+                        elements.push(self.parse_expression()?);
+
+                        if !self.match_any(&[TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                }
+
+                self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+                return Ok(Expr::List(elements));
+            }
+            TokenType::LeftParen => {
+                self.advance();
+                let expr = self.parse_expression()?;
+                self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+                return Ok(Expr::Grouping(Box::new(expr)));
+            }
+            _ => return Err(Parser::error(self.peek(), "Expect expression.")),
+        };
+
+        self.advance();
+        Ok(Expr::Literal(literal))
+    }
+
+    /// Discards tokens until just after a `;` or just before a keyword that starts a statement
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().kind == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().kind {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    /// Advances past the next token if it's any of `kinds`
+    fn match_any(&mut self, kinds: &[TokenType]) -> bool {
+        if kinds.iter().any(|kind| self.check(kind)) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn consume(&mut self, kind: TokenType, message: &str) -> Result<&Token, ParseError> {
+        if self.check(&kind) {
+            Ok(self.advance())
+        } else {
+            Err(Parser::error(self.peek(), message))
+        }
+    }
+
+    /// Identifiers carry their name, so they can't be matched with `consume`
+    fn consume_identifier(&mut self, message: &str) -> Result<&Token, ParseError> {
+        if !self.is_at_end() && matches!(self.peek().kind, TokenType::Identifier(_)) {
+            Ok(self.advance())
+        } else {
+            Err(Parser::error(self.peek(), message))
+        }
+    }
+
+    fn error(token: &Token, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            line: token.line,
+            column: token.column,
+            token: token.text().into_owned(),
+        }
+    }
+
+    fn check(&self, kind: &TokenType) -> bool {
+        !self.is_at_end() && self.peek().kind == *kind
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    /// Whether every token but the final `Eof` has been consumed
+    pub fn is_at_end(&self) -> bool {
+        self.peek().kind == TokenType::Eof
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    /// Like `check`, but for the token after the current one
+    fn check_next(&self, kind: &TokenType) -> bool {
+        matches!(self.tokens.get(self.current + 1), Some(token) if token.kind == *kind)
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::parser::*;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Result<Expr, ParseError> {
+        Parser::new(Scanner::new(source).scan_tokens()).parse_expression()
+    }
+
+    fn parse_statements(source: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        Parser::new(Scanner::new(source).scan_tokens()).parse()
+    }
+
+    #[test]
+    fn precedence() {
+        let expr = parse("1 + 2 * 3").unwrap();
+
+        match expr {
+            Expr::Binary { left, op, right } => {
+                assert_eq!(*left, Expr::Literal(LiteralValue::Number(1.0)));
+                assert_eq!(op.kind, TokenType::Plus);
+
+                match *right {
+                    Expr::Binary { left, op, right } => {
+                        assert_eq!(*left, Expr::Literal(LiteralValue::Number(2.0)));
+                        assert_eq!(op.kind, TokenType::Asterisk);
+                        assert_eq!(*right, Expr::Literal(LiteralValue::Number(3.0)));
+                    }
+                    _ => {
+                        unreachable!();
+                    }
+                }
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn grouping() {
+        let expr = parse("(1 + 2) * 3").unwrap();
+
+        match expr {
+            Expr::Binary { left, op, right } => {
+                assert_eq!(op.kind, TokenType::Asterisk);
+                assert_eq!(*right, Expr::Literal(LiteralValue::Number(3.0)));
+
+                match *left {
+                    Expr::Grouping(inner) => {
+                        assert!(matches!(*inner, Expr::Binary { .. }));
+                    }
+                    _ => {
+                        unreachable!();
+                    }
+                }
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn missing_expression() {
+        assert!(parse("1 +").is_err());
+    }
+
+    #[test]
+    fn missing_close_paren() {
+        let error = parse("(1 +\n 2").unwrap_err();
+
+        assert!(error.message.contains("')'"));
+        assert_eq!(error.line, 2);
+        assert_eq!(error.token, "");
+        assert_eq!(
+            error.to_string(),
+            "[line 2] Error at end: Expect ')' after expression."
+        );
+    }
+
+    #[test]
+    fn error_names_found_token() {
+        let error = parse("(1 + 2;").unwrap_err();
+
+        assert_eq!(error.token, ";");
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at ';': Expect ')' after expression."
+        );
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let errors = parse_statements("1 + ;\n2;\n(3 * 4;").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+
+        assert_eq!(errors[0].line, 1);
+        assert_eq!(errors[0].token, ";");
+        assert_eq!(errors[1].line, 3);
+        assert!(errors[1].message.contains("')'"));
+    }
+
+    #[test]
+    fn statements() {
+        let statements = parse_statements("print 1; var x = 2; var y; 3;").unwrap();
+
+        assert_eq!(statements.len(), 4);
+
+        assert_eq!(
+            statements[0],
+            Stmt::Print(Expr::Literal(LiteralValue::Number(1.0)))
+        );
+
+        match &statements[1] {
+            Stmt::Var { name, initializer } => {
+                assert_eq!(name.text(), "x");
+                assert_eq!(*initializer, Some(Expr::Literal(LiteralValue::Number(2.0))));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+
+        assert!(matches!(
+            statements[2],
+            Stmt::Var {
+                initializer: None,
+                ..
+            }
+        ));
+        assert_eq!(
+            statements[3],
+            Stmt::Expression(Expr::Literal(LiteralValue::Number(3.0)))
+        );
+    }
+
+    #[test]
+    fn variable() {
+        let expr = parse("x + 1").unwrap();
+
+        match expr {
+            Expr::Binary { left, .. } => match *left {
+                Expr::Variable { name, .. } => {
+                    assert_eq!(name.text(), "x");
+                }
+                _ => {
+                    unreachable!();
+                }
+            },
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn assignment() {
+        let expr = parse("a = 1").unwrap();
+
+        match expr {
+            Expr::Assign { name, value, .. } => {
+                assert_eq!(name.text(), "a");
+                assert_eq!(*value, Expr::Literal(LiteralValue::Number(1.0)));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        let expr = parse("a = b = 1").unwrap();
+
+        match expr {
+            Expr::Assign { name, value, .. } => {
+                assert_eq!(name.text(), "a");
+                assert!(matches!(*value, Expr::Assign { name, .. } if name.text() == "b"));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_assignment_target() {
+        let error = parse("1 + 2 = 3").unwrap_err();
+
+        assert_eq!(error.message, "Invalid assignment target.");
+        assert_eq!(error.token, "=");
+    }
+
+    #[test]
+    fn block() {
+        let statements = parse_statements("{ var x = 1; print x; }").unwrap();
+
+        assert_eq!(statements.len(), 1);
+
+        match &statements[0] {
+            Stmt::Block(inner) => {
+                assert_eq!(inner.len(), 2);
+                assert!(matches!(inner[0], Stmt::Var { .. }));
+                assert!(matches!(inner[1], Stmt::Print(_)));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn nested_block() {
+        let statements = parse_statements("{ { print 1; } }").unwrap();
+
+        match &statements[0] {
+            Stmt::Block(inner) => {
+                assert!(matches!(&inner[..], [Stmt::Block(_)]));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_block() {
+        let errors = parse_statements("{ print 1;").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Expect '}' after block.");
+    }
+
+    #[test]
+    fn if_statement() {
+        let statements = parse_statements("if (x) print 1;").unwrap();
+
+        match &statements[0] {
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                assert!(matches!(condition, Expr::Variable { .. }));
+                assert!(matches!(**then_branch, Stmt::Print(_)));
+                assert!(else_branch.is_none());
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn if_else_statement() {
+        let statements = parse_statements("if (x) print 1; else print 2;").unwrap();
+
+        match &statements[0] {
+            Stmt::If { else_branch, .. } => {
+                assert_eq!(
+                    else_branch.as_deref(),
+                    Some(&Stmt::Print(Expr::Literal(LiteralValue::Number(2.0))))
+                );
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn dangling_else() {
+        let statements = parse_statements("if (a) if (b) print 1; else print 2;").unwrap();
+
+        match &statements[0] {
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                assert!(else_branch.is_none());
+                assert!(matches!(
+                    **then_branch,
+                    Stmt::If {
+                        else_branch: Some(_),
+                        ..
+                    }
+                ));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn while_statement() {
+        let statements = parse_statements("while (x < 10) x = x + 1;").unwrap();
+
+        match &statements[0] {
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                assert!(matches!(condition, Expr::Binary { op, .. } if op.kind == TokenType::Less));
+                assert!(increment.is_none());
+                assert!(matches!(**body, Stmt::Expression(Expr::Assign { .. })));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn while_missing_paren() {
+        let errors = parse_statements("while x < 10) x = x + 1;").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect '(' after 'while'.");
+    }
+
+    #[test]
+    fn for_desugars_to_while() {
+        let statements = parse_statements("for (var i = 0; i < 3; i = i + 1) print i;").unwrap();
+
+        assert_eq!(statements.len(), 1);
+
+        match &statements[0] {
+            Stmt::Block(outer) => {
+                assert!(matches!(&outer[0], Stmt::Var { name, .. } if name.text() == "i"));
+
+                match &outer[1] {
+                    Stmt::While {
+                        condition,
+                        body,
+                        increment,
+                    } => {
+                        assert!(matches!(condition, Expr::Binary { .. }));
+                        assert!(matches!(**body, Stmt::Print(_)));
+                        assert!(matches!(increment, Some(Expr::Assign { .. })));
+                    }
+                    _ => {
+                        unreachable!();
+                    }
+                }
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let statements = parse_statements("while (true) { break; continue; }").unwrap();
+
+        match &statements[0] {
+            Stmt::While { body, .. } => match &**body {
+                Stmt::Block(inner) => {
+                    assert!(matches!(&inner[0], Stmt::Break { keyword } if keyword.line == 1));
+                    assert!(matches!(inner[1], Stmt::Continue { .. }));
+                }
+                _ => {
+                    unreachable!();
+                }
+            },
+            _ => {
+                unreachable!();
+            }
+        }
+
+        let errors = parse_statements("while (true) break").unwrap_err();
+        assert_eq!(errors[0].message, "Expect ';' after 'break'.");
+    }
+
+    #[test]
+    fn empty_for() {
+        let statements = parse_statements("for (;;) {}").unwrap();
+
+        assert_eq!(
+            statements,
+            vec![Stmt::While {
+                condition: Expr::Literal(LiteralValue::Bool(true)),
+                body: Box::new(Stmt::Block(vec![])),
+                increment: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn logical() {
+        let expr = parse("a or b and c").unwrap();
+
+        match expr {
+            Expr::Logical { op, right, .. } => {
+                assert_eq!(op.kind, TokenType::Or);
+                assert!(matches!(*right, Expr::Logical { op, .. } if op.kind == TokenType::And));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn call_without_args() {
+        let expr = parse("f()").unwrap();
+
+        match expr {
+            Expr::Call { callee, args, .. } => {
+                assert!(matches!(*callee, Expr::Variable { name, .. } if name.text() == "f"));
+                assert!(args.is_empty());
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn call_with_args() {
+        let expr = parse("f(1, 2, 3)").unwrap();
+
+        match expr {
+            Expr::Call { paren, args, .. } => {
+                assert_eq!(paren.kind, TokenType::RightParen);
+                assert_eq!(args.len(), 3);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn chained_calls() {
+        let expr = parse("g()()").unwrap();
+
+        match expr {
+            Expr::Call { callee, .. } => {
+                assert!(matches!(*callee, Expr::Call { .. }));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn too_many_args() {
+        let args = vec!["1"; 256].join(", ");
+
+        let error = parse(&format!("f({})", args)).unwrap_err();
+
+        assert_eq!(error.message, "Can't have more than 255 arguments.");
+        assert!(parse(&format!("f({})", vec!["1"; 255].join(", "))).is_ok());
+    }
+
+    #[test]
+    fn function_without_params() {
+        let statements = parse_statements("fun f() { print 1; }").unwrap();
+
+        match &statements[0] {
+            Stmt::Function { name, params, body } => {
+                assert_eq!(name.text(), "f");
+                assert!(params.is_empty());
+                assert_eq!(body.len(), 1);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn function_with_params() {
+        let statements = parse_statements("fun add(a, b) { print a + b; }").unwrap();
+
+        match &statements[0] {
+            Stmt::Function { params, .. } => {
+                let names: Vec<Cow<str>> = params.iter().map(|param| param.text()).collect();
+                assert_eq!(names, vec!["a", "b"]);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn function_missing_name() {
+        let errors = parse_statements("fun 1(a) { }").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect function name.");
+
+        // Without a name it's an anonymous function, which as a statement needs a `;`
+        let errors = parse_statements("fun (a) { }").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect ';' after expression.");
+    }
+
+    #[test]
+    fn return_without_value() {
+        let statements = parse_statements("return;").unwrap();
+
+        match &statements[0] {
+            Stmt::Return { keyword, value } => {
+                assert_eq!(keyword.kind, TokenType::Return);
+                assert!(value.is_none());
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn return_with_value() {
+        let statements = parse_statements("return 1 + 2;").unwrap();
+
+        assert!(matches!(
+            &statements[0],
+            Stmt::Return {
+                value: Some(Expr::Binary { .. }),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn return_missing_semicolon() {
+        let errors = parse_statements("return 1").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect ';' after return value.");
+    }
+
+    #[test]
+    fn class() {
+        let statements =
+            parse_statements("class Foo { bar() { return 1; } baz(a) { print a; } }").unwrap();
+
+        match &statements[0] {
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                assert_eq!(name.text(), "Foo");
+                assert!(superclass.is_none());
+
+                let names: Vec<Cow<str>> = methods
+                    .iter()
+                    .map(|method| match method {
+                        Stmt::Function { name, .. } => name.text(),
+                        _ => unreachable!(),
+                    })
+                    .collect();
+                assert_eq!(names, vec!["bar", "baz"]);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn class_missing_braces() {
+        let errors = parse_statements("class Foo bar() {}").unwrap_err();
+        assert_eq!(errors[0].message, "Expect '{' before class body.");
+
+        let errors = parse_statements("class Foo { bar() {}").unwrap_err();
+        assert_eq!(errors[0].message, "Expect '}' after class body.");
+    }
+
+    #[test]
+    fn class_with_superclass() {
+        let statements = parse_statements("class B < A { }").unwrap();
+
+        match &statements[0] {
+            Stmt::Class { superclass, .. } => {
+                assert!(
+                    matches!(superclass, Some(Expr::Variable { name, .. }) if name.text() == "A")
+                );
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn class_missing_superclass_name() {
+        let errors = parse_statements("class B < { }").unwrap_err();
+
+        assert_eq!(errors[0].message, "Expect superclass name.");
+    }
+
+    #[test]
+    fn get() {
+        let expr = parse("a.b").unwrap();
+
+        match expr {
+            Expr::Get { object, name } => {
+                assert!(matches!(*object, Expr::Variable { ref name, .. } if name.text() == "a"));
+                assert_eq!(name.text(), "b");
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn chained_get() {
+        let expr = parse("a.b.c").unwrap();
+
+        match expr {
+            Expr::Get { object, name } => {
+                assert_eq!(name.text(), "c");
+                assert!(matches!(*object, Expr::Get { ref name, .. } if name.text() == "b"));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn get_missing_name() {
+        let error = parse("a.").unwrap_err();
+
+        assert_eq!(error.message, "Expect property name after '.'.");
+    }
+
+    #[test]
+    fn set() {
+        let expr = parse("a.b = 3").unwrap();
+
+        match expr {
+            Expr::Set {
+                object,
+                name,
+                value,
+            } => {
+                assert!(matches!(*object, Expr::Variable { .. }));
+                assert_eq!(name.text(), "b");
+                assert_eq!(*value, Expr::Literal(LiteralValue::Number(3.0)));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn set_on_call_result() {
+        let expr = parse("a.b().c = 1").unwrap();
+
+        match expr {
+            Expr::Set { object, name, .. } => {
+                assert_eq!(name.text(), "c");
+                assert!(matches!(*object, Expr::Call { .. }));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn anonymous_function() {
+        let expr = parse("fun (a, b) { return a + b; }").unwrap();
+
+        match expr {
+            Expr::Function { params, body, .. } => {
+                let names: Vec<Cow<str>> = params.iter().map(|param| param.text()).collect();
+                assert_eq!(names, vec!["a", "b"]);
+                assert!(matches!(body[0], Stmt::Return { .. }));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn anonymous_function_statement() {
+        let statements = parse_statements("fun () {};\nfun f() {}").unwrap();
+
+        assert!(matches!(
+            statements[0],
+            Stmt::Expression(Expr::Function { .. })
+        ));
+        assert!(matches!(statements[1], Stmt::Function { .. }));
+
+        let error = parse("fun a").unwrap_err();
+        assert_eq!(error.message, "Expect '(' after 'fun'.");
+    }
+
+    #[test]
+    fn ternary() {
+        let expr = parse("true ? 1 : 2").unwrap();
+
+        assert_eq!(
+            expr,
+            Expr::Ternary {
+                cond: Box::new(Expr::Literal(LiteralValue::Bool(true))),
+                then_expr: Box::new(Expr::Literal(LiteralValue::Number(1.0))),
+                else_expr: Box::new(Expr::Literal(LiteralValue::Number(2.0))),
+            }
+        );
+    }
+
+    #[test]
+    fn ternary_is_right_associative() {
+        let expr = parse("a ? b : c ? d : e").unwrap();
+
+        match expr {
+            Expr::Ternary {
+                cond, else_expr, ..
+            } => {
+                assert!(matches!(*cond, Expr::Variable { ref name, .. } if name.text() == "a"));
+                assert!(matches!(*else_expr, Expr::Ternary { .. }));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn ternary_missing_colon() {
+        let error = parse("a ? b").unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Expect ':' after then branch of conditional expression."
+        );
+    }
+
+    #[test]
+    fn list_literal() {
+        let expr = parse("[1, \"a\", []]").unwrap();
+
+        match expr {
+            Expr::List(elements) => {
+                assert_eq!(elements.len(), 3);
+                assert_eq!(elements[0], Expr::Literal(LiteralValue::Number(1.0)));
+                assert_eq!(elements[2], Expr::List(vec![]));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn unclosed_list() {
+        let error = parse("[1, 2").unwrap_err();
+
+        assert_eq!(error.message, "Expect ']' after list elements.");
+    }
+
+    #[test]
+    fn index_and_set_index() {
+        let expr = parse("a[0][1] = 2").unwrap();
+
+        match expr {
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                assert!(matches!(*object, Expr::Index { .. }));
+                assert_eq!(*index, Expr::Literal(LiteralValue::Number(1.0)));
+                assert_eq!(*value, Expr::Literal(LiteralValue::Number(2.0)));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn this() {
+        let expr = parse("this.x").unwrap();
+
+        match expr {
+            Expr::Get { object, name } => {
+                assert_eq!(name.text(), "x");
+                assert!(matches!(*object, Expr::This { .. }));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn super_call() {
+        let expr = parse("super.cook()").unwrap();
+
+        match expr {
+            Expr::Call { callee, .. } => {
+                assert!(matches!(*callee, Expr::Super { method, .. } if method.text() == "cook"));
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_super() {
+        let error = parse("super.").unwrap_err();
+        assert_eq!(error.message, "Expect superclass method name.");
+
+        let error = parse("super x").unwrap_err();
+        assert_eq!(error.message, "Expect '.' after 'super'.");
+    }
+}
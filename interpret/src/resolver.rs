@@ -0,0 +1,508 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::expr::Expr;
+use crate::scanner::Token;
+use crate::stmt::Stmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    /// Lexeme of the offending token
+    pub token: String,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error at '{}': {}",
+            self.line, self.token, self.message
+        )
+    }
+}
+
+/// Kind of function whose body is being resolved
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum FunctionType {
+    #[default]
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+/// Kind of class whose body is being resolved
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum ClassType {
+    #[default]
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass run between parsing and interpreting, it records how many scopes away each local
+/// variable is declared
+#[derive(Default)]
+pub struct Resolver {
+    /// Local scopes, innermost last. A name maps to whether its initializer has been resolved
+    scopes: Vec<HashMap<String, bool>>,
+    errors: Vec<ResolveError>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    /// Loops around the code being resolved, within the current function
+    loop_depth: usize,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver::default()
+    }
+
+    pub fn resolve(mut self, statements: &[Stmt]) -> Result<(), Vec<ResolveError>> {
+        self.resolve_statements(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expression(expr),
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let enclosing_class = std::mem::replace(&mut self.current_class, ClassType::Class);
+                self.declare(name);
+                self.define(name);
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable {
+                        name: superclass_name,
+                        ..
+                    } = superclass
+                    {
+                        if superclass_name.text() == name.text() {
+                            self.error(superclass_name, "A class can't inherit from itself.");
+                        }
+                    }
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expression(superclass);
+
+                    self.scopes.push(HashMap::new());
+                    self.scopes
+                        .last_mut()
+                        .unwrap()
+                        .insert("super".to_string(), true);
+                }
+
+                // Methods are closures over a scope holding `this`
+                self.scopes.push(HashMap::new());
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert("this".to_string(), true);
+                for method in methods {
+                    if let Stmt::Function { name, params, body } = method {
+                        let kind = if name.text() == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+                        self.resolve_function(params, body, kind);
+                    }
+                }
+                self.scopes.pop();
+                if superclass.is_some() {
+                    self.scopes.pop();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Function { name, params, body } => {
+                // Defined before the body so the function can recurse
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Stmt::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expression(value);
+                }
+            }
+            Stmt::Block(statements) => {
+                self.scopes.push(HashMap::new());
+                self.resolve_statements(statements);
+                self.scopes.pop();
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.resolve_expression(condition);
+                self.loop_depth += 1;
+                self.resolve_statement(body);
+                self.loop_depth -= 1;
+                if let Some(increment) = increment {
+                    self.resolve_expression(increment);
+                }
+            }
+            Stmt::Break { keyword } => {
+                if self.loop_depth == 0 {
+                    self.error(keyword, "Can't use 'break' outside of a loop.");
+                }
+            }
+            Stmt::Continue { keyword } => {
+                if self.loop_depth == 0 {
+                    self.error(keyword, "Can't use 'continue' outside of a loop.");
+                }
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt], kind: FunctionType) {
+        let enclosing_function = std::mem::replace(&mut self.current_function, kind);
+        // Loops outside the function can't be broken out of from inside it
+        let enclosing_loops = std::mem::replace(&mut self.loop_depth, 0);
+
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(body);
+        self.scopes.pop();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loops;
+    }
+
+    fn resolve_expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(_) => {}
+            Expr::Super { keyword, depth, .. } => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => {
+                        self.error(keyword, "Can't use 'super' in a class with no superclass.")
+                    }
+                    ClassType::Subclass => {}
+                }
+                self.resolve_local(keyword, depth);
+            }
+            Expr::This { keyword, depth } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                }
+                self.resolve_local(keyword, depth);
+            }
+            Expr::Grouping(inner) => self.resolve_expression(inner),
+            Expr::Unary { right, .. } => self.resolve_expression(right),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expr::Variable { name, depth } => {
+                let in_initializer = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&*name.text()))
+                    == Some(&false);
+                if in_initializer {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+                self.resolve_local(name, depth);
+            }
+            Expr::Assign { name, value, depth } => {
+                self.resolve_expression(value);
+                self.resolve_local(name, depth);
+            }
+            Expr::Call { callee, args, .. } => {
+                self.resolve_expression(callee);
+                for arg in args {
+                    self.resolve_expression(arg);
+                }
+            }
+            Expr::Get { object, .. } => self.resolve_expression(object),
+            Expr::Set { object, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+            }
+            Expr::Function { params, body, .. } => {
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Expr::Ternary {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                self.resolve_expression(cond);
+                self.resolve_expression(then_expr);
+                self.resolve_expression(else_expr);
+            }
+            Expr::List(elements) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+        }
+    }
+
+    /// Records the distance to the innermost scope declaring `name`, globals are left unresolved
+    fn resolve_local(&mut self, name: &Token, depth: &Cell<Option<usize>>) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&*name.text()));
+        depth.set(found);
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let redeclared = match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.text().into_owned(), false).is_some(),
+            None => false,
+        };
+        if redeclared {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.text().into_owned(), true);
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ResolveError {
+            message: message.to_string(),
+            line: token.line,
+            column: token.column,
+            token: token.text().into_owned(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parser;
+    use crate::resolver::*;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str) -> Result<Vec<Stmt>, Vec<ResolveError>> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .parse()
+            .unwrap();
+        Resolver::new().resolve(&statements)?;
+        Ok(statements)
+    }
+
+    #[test]
+    fn closure_depth() {
+        let statements = resolve("var a; { var b; fun f() { print a; print b; } }").unwrap();
+
+        let body = match &statements[1] {
+            Stmt::Block(inner) => match &inner[1] {
+                Stmt::Function { body, .. } => body.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        match (&body[0], &body[1]) {
+            (
+                Stmt::Print(Expr::Variable { depth: a, .. }),
+                Stmt::Print(Expr::Variable { depth: b, .. }),
+            ) => {
+                assert_eq!(a.get(), None);
+                assert_eq!(b.get(), Some(1));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn anonymous_function_scope() {
+        let statements = resolve("{ var a; var f = fun (b) { print a; print b; }; }").unwrap();
+
+        let body = match &statements[0] {
+            Stmt::Block(inner) => match &inner[1] {
+                Stmt::Var {
+                    initializer: Some(Expr::Function { body, .. }),
+                    ..
+                } => body.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        match (&body[0], &body[1]) {
+            (
+                Stmt::Print(Expr::Variable { depth: a, .. }),
+                Stmt::Print(Expr::Variable { depth: b, .. }),
+            ) => {
+                assert_eq!(a.get(), Some(1));
+                assert_eq!(b.get(), Some(0));
+            }
+            _ => unreachable!(),
+        }
+
+        let errors = resolve("var f = fun () { this; };").unwrap_err();
+        assert_eq!(errors[0].message, "Can't use 'this' outside of a class.");
+    }
+
+    #[test]
+    fn own_initializer() {
+        let errors = resolve("var a = 1; { var a = a; }").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at 'a': Can't read local variable in its own initializer."
+        );
+    }
+
+    #[test]
+    fn global_own_initializer() {
+        assert!(resolve("var a = a;").is_ok());
+    }
+
+    #[test]
+    fn duplicate_local() {
+        // Parameters share the scope of the function body
+        let errors = resolve("fun f(a) { var a; }\n{ var b; var b; }\nvar c; var c;").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].message,
+            "Already a variable with this name in this scope."
+        );
+        assert_eq!(errors[0].line, 1);
+        assert_eq!(errors[1].line, 2);
+    }
+
+    #[test]
+    fn top_level_return() {
+        let errors = resolve("print 1;\nreturn;").unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 2] Error at 'return': Can't return from top-level code."
+        );
+        assert!(resolve("fun f() { { return 1; } }").is_ok());
+    }
+
+    #[test]
+    fn loop_control_outside_loop() {
+        let errors = resolve("break;\nwhile (true) { fun f() { continue; } }").unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at 'break': Can't use 'break' outside of a loop."
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "[line 2] Error at 'continue': Can't use 'continue' outside of a loop."
+        );
+        assert!(resolve("for (;;) { if (true) { break; } continue; }").is_ok());
+    }
+
+    #[test]
+    fn this_outside_class() {
+        let errors = resolve("print this;\nfun f() { return this; }").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at 'this': Can't use 'this' outside of a class."
+        );
+        assert!(resolve("class A { f() { return this; } }").is_ok());
+    }
+
+    #[test]
+    fn return_value_from_initializer() {
+        let errors = resolve("class A { init() { return 1; } }").unwrap_err();
+
+        assert_eq!(
+            errors[0].message,
+            "Can't return a value from an initializer."
+        );
+        assert!(resolve("class A { init() { return; } }").is_ok());
+    }
+
+    #[test]
+    fn super_without_superclass() {
+        let errors = resolve("class A { f() { super.f(); } }").unwrap_err();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at 'super': Can't use 'super' in a class with no superclass."
+        );
+    }
+
+    #[test]
+    fn super_outside_class() {
+        let errors = resolve("super.f();").unwrap_err();
+
+        assert_eq!(errors[0].message, "Can't use 'super' outside of a class.");
+    }
+
+    #[test]
+    fn inherit_from_itself() {
+        let errors = resolve("class A < A {}").unwrap_err();
+
+        assert_eq!(errors[0].message, "A class can't inherit from itself.");
+        assert!(resolve("class A {} class B < A { f() { super.f(); } }").is_ok());
+    }
+}
@@ -1,191 +1,711 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
 
-#[derive(Debug)]
-pub enum Token {
+use crate::interner::Interner;
+
+/// With the `serde` feature this serializes like the AST, as `{"type": "<variant>", "value": <data>}`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
+pub enum TokenType {
     // Single Characters
-    LeftParen { line: usize },
-    RightParen { line: usize },
-    LeftBrace { line: usize },
-    RightBrace { line: usize },
-    Comma { line: usize },
-    Dot { line: usize },
-    Minus { line: usize },
-    Plus { line: usize },
-    Semicolon { line: usize },
-    Slash { line: usize },
-    Asterisk { line: usize },
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Dot,
+    Minus,
+    Plus,
+    Semicolon,
+    Slash,
+    Asterisk,
+    Percent,
+    Question,
+    Colon,
 
     // Equality
-    Bang { line: usize },
-    BangEqual { line: usize },
-    Equal { line: usize },
-    EqualEqual { line: usize },
-    Greater { line: usize },
-    GreaterEqual { line: usize },
-    Less { line: usize },
-    LessEqual { line: usize },
+    Bang,
+    BangEqual,
+    Equal,
+    EqualEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
 
     // Literal
-    Identifier { line: usize, literal: String },
-    String { line: usize, literal: String },
-    Number { line: usize, literal: f64 },
+    /// Holds the interned name, every occurrence of a name in one scan shares it
+    Identifier(Rc<str>),
+    String(String),
+    Number(f64),
 
     //Keyword
-    And { line: usize },
-    Class { line: usize },
-    Else { line: usize },
-    False { line: usize },
-    Fun { line: usize },
-    For { line: usize },
-    If { line: usize },
-    Nil { line: usize },
-    Or { line: usize },
-    Print { line: usize },
-    Return { line: usize },
-    Super { line: usize },
-    This { line: usize },
-    True { line: usize },
-    Var { line: usize },
-    While { line: usize },
+    And,
+    Break,
+    Class,
+    Continue,
+    Else,
+    False,
+    Fun,
+    For,
+    If,
+    Nil,
+    Or,
+    Print,
+    Return,
+    Super,
+    This,
+    True,
+    Var,
+    While,
+
+    Eof,
+
+    Invalid(String),
+}
+
+/// With the `serde` feature this serializes as `{kind, lexeme, line, column}`, the lexeme being
+/// rebuilt by `text`. Use `with_source` for the exact text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenType,
+    /// Byte offsets of the token's text in the source, see `lexeme`
+    pub span: Range<usize>,
+    /// Location of the first character of the token, both 1-based
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TokenType::LeftParen => "LEFT_PAREN",
+            TokenType::RightParen => "RIGHT_PAREN",
+            TokenType::LeftBrace => "LEFT_BRACE",
+            TokenType::RightBrace => "RIGHT_BRACE",
+            TokenType::LeftBracket => "LEFT_BRACKET",
+            TokenType::RightBracket => "RIGHT_BRACKET",
+            TokenType::Comma => "COMMA",
+            TokenType::Dot => "DOT",
+            TokenType::Minus => "MINUS",
+            TokenType::Plus => "PLUS",
+            TokenType::Semicolon => "SEMICOLON",
+            TokenType::Slash => "SLASH",
+            TokenType::Asterisk => "ASTERISK",
+            TokenType::Percent => "PERCENT",
+            TokenType::Question => "QUESTION",
+            TokenType::Colon => "COLON",
+            TokenType::Bang => "BANG",
+            TokenType::BangEqual => "BANG_EQUAL",
+            TokenType::Equal => "EQUAL",
+            TokenType::EqualEqual => "EQUAL_EQUAL",
+            TokenType::Greater => "GREATER",
+            TokenType::GreaterEqual => "GREATER_EQUAL",
+            TokenType::Less => "LESS",
+            TokenType::LessEqual => "LESS_EQUAL",
+            TokenType::Identifier(_) => "IDENTIFIER",
+            TokenType::String(_) => "STRING",
+            TokenType::Number(_) => "NUMBER",
+            TokenType::And => "AND",
+            TokenType::Break => "BREAK",
+            TokenType::Class => "CLASS",
+            TokenType::Continue => "CONTINUE",
+            TokenType::Else => "ELSE",
+            TokenType::False => "FALSE",
+            TokenType::Fun => "FUN",
+            TokenType::For => "FOR",
+            TokenType::If => "IF",
+            TokenType::Nil => "NIL",
+            TokenType::Or => "OR",
+            TokenType::Print => "PRINT",
+            TokenType::Return => "RETURN",
+            TokenType::Super => "SUPER",
+            TokenType::This => "THIS",
+            TokenType::True => "TRUE",
+            TokenType::Var => "VAR",
+            TokenType::While => "WHILE",
+            TokenType::Eof => "EOF",
+            TokenType::Invalid(_) => "INVALID",
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl Token {
+    /// The token's text, `source` has to be what it was scanned from
+    pub fn lexeme<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.clone()]
+    }
+
+    /// Pairs the token with the source it was scanned from
+    pub fn with_source<'a>(&'a self, source: &'a str) -> SourceToken<'a> {
+        SourceToken {
+            token: self,
+            source,
+        }
+    }
+
+    /// The token's text rebuilt from its kind, for when the source is gone, like function names
+    /// at runtime or errors. Numbers come back in their shortest form, e.g. `1_000` as `1000`
+    pub fn text(&self) -> Cow<'_, str> {
+        let text = match &self.kind {
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Semicolon => ";",
+            TokenType::Slash => "/",
+            TokenType::Asterisk => "*",
+            TokenType::Percent => "%",
+            TokenType::Question => "?",
+            TokenType::Colon => ":",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::Identifier(name) => &**name,
+            TokenType::String(literal) => return Cow::Owned(escape(literal)),
+            TokenType::Number(number) => return Cow::Owned(number.to_string()),
+            TokenType::And => "and",
+            TokenType::Break => "break",
+            TokenType::Class => "class",
+            TokenType::Continue => "continue",
+            TokenType::Else => "else",
+            TokenType::False => "false",
+            TokenType::Fun => "fun",
+            TokenType::For => "for",
+            TokenType::If => "if",
+            TokenType::Nil => "nil",
+            TokenType::Or => "or",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Super => "super",
+            TokenType::This => "this",
+            TokenType::True => "true",
+            TokenType::Var => "var",
+            TokenType::While => "while",
+            TokenType::Eof | TokenType::Invalid(_) => "",
+        };
+
+        Cow::Borrowed(text)
+    }
+}
+
+/// A token along with its source, so it has the exact text the token was scanned from
+#[derive(Debug, Clone, Copy)]
+pub struct SourceToken<'a> {
+    pub token: &'a Token,
+    pub source: &'a str,
+}
+
+/// Shape of a serialized token, also used by `--tokens-json`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct TokenJson<'a> {
+    kind: &'a TokenType,
+    lexeme: Cow<'a, str>,
+    line: usize,
+    column: usize,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Token {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TokenJson {
+            kind: &self.kind,
+            lexeme: self.text(),
+            line: self.line,
+            column: self.column,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SourceToken<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TokenJson {
+            kind: &self.token.kind,
+            lexeme: Cow::Borrowed(self.token.lexeme(self.source)),
+            line: self.token.line,
+            column: self.token.column,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Quotes a string literal, escaping it the way the scanner reads it back
+pub(crate) fn escape(literal: &str) -> String {
+    let mut quoted = String::with_capacity(literal.len() + 2);
+    quoted.push('"');
+
+    for character in literal.chars() {
+        match character {
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            _ => quoted.push(character),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Prints the kind followed by the token's text, e.g. `NUMBER 3.14`
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_token(f, &self.kind, &self.text())
+    }
+}
+
+/// Like `Token`'s, but with the exact source text, e.g. `NUMBER 1_000` rather than `NUMBER 1000`
+impl fmt::Display for SourceToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_token(f, &self.token.kind, self.token.lexeme(self.source))
+    }
+}
 
-    Eof { line: usize },
+fn write_token(f: &mut fmt::Formatter<'_>, kind: &TokenType, lexeme: &str) -> fmt::Result {
+    match lexeme {
+        "" => write!(f, "{}", kind),
+        lexeme => write!(f, "{} {}", kind, lexeme),
+    }
+}
 
-    Invalid { message: String, line: usize },
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
 }
 
-pub struct Scanner {
-    source: String,
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}:{}] Error: {}",
+            self.line, self.column, self.message
+        )
+    }
 }
 
-impl Scanner {
-    pub fn new(source: String) -> Self {
-        Scanner { source }
+/// Peekable character stream over the source that keeps track of its position
+struct Cursor<'a> {
+    source: &'a str,
+    /// Byte offset of the next character
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str) -> Self {
+        Cursor {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    /// Looks one character past `peek`
+    fn peek_next(&self) -> Option<char> {
+        self.source[self.offset..].chars().nth(1)
     }
 
-    pub fn scan_tokens(&self) -> Vec<Token> {
+    fn next_if(&mut self, func: impl FnOnce(&char) -> bool) -> Option<char> {
+        let character = self.peek().filter(func)?;
+        self.advance(character);
+        Some(character)
+    }
+
+    fn next_if_eq(&mut self, expected: &char) -> Option<char> {
+        self.next_if(|c| c == expected)
+    }
+
+    fn advance(&mut self, character: char) {
+        self.offset += character.len_utf8();
+
+        if character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    /// Source text consumed since the byte offset `start`
+    fn slice_from(&self, start: usize) -> &'a str {
+        &self.source[start..self.offset]
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.advance(character);
+        Some(character)
+    }
+}
+
+/// Lazily turns source into tokens, ending with a single `TokenType::Eof`
+pub struct Scanner<'a> {
+    cursor: Cursor<'a>,
+    interner: Interner,
+    finished: bool,
+}
+
+impl<'a> Scanner<'a> {
+    /// The scanner only borrows the source, so it can be shared with error reporting
+    pub fn new(source: &'a str) -> Self {
+        let mut cursor = Cursor::new(source);
+
+        // Some editors start files with a byte order mark, skip it without counting a column so
+        // offsets still line up with the source
+        if source.starts_with('\u{feff}') {
+            cursor.offset = '\u{feff}'.len_utf8();
+        }
+
+        // Let scripts be executable with a `#!` interpreter line, the newline is left for the
+        // scanner to count
+        if source[cursor.offset..].starts_with("#!") {
+            while cursor.next_if(|&c| c != '\n').is_some() {}
+        }
+
+        Scanner {
+            cursor,
+            interner: Interner::new(),
+            finished: false,
+        }
+    }
+
+    /// Scans the whole source, collecting every lexing error instead of leaving them in the token
+    /// stream
+    pub fn scan(self) -> Result<Vec<Token>, Vec<ScanError>> {
+        let (tokens, errors) = self.scan_all();
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Like `scan`, but keeps the valid tokens when there are errors so parsing can still go on
+    pub fn scan_all(self) -> (Vec<Token>, Vec<ScanError>) {
         let mut tokens: Vec<Token> = vec![];
+        let mut errors: Vec<ScanError> = vec![];
 
-        let mut line: usize = 0;
+        for token in self {
+            match token.kind {
+                TokenType::Invalid(message) => errors.push(ScanError {
+                    message,
+                    line: token.line,
+                    column: token.column,
+                }),
+                _ => tokens.push(token),
+            }
+        }
+
+        (tokens, errors)
+    }
+
+    /// Scans the whole source, errors show up as `TokenType::Invalid` tokens in place
+    pub fn scan_tokens(self) -> Vec<Token> {
+        self.collect()
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Token;
 
-        let mut char_iter_peekable = self.source.chars().peekable();
+    fn next(&mut self) -> Option<Token> {
+        let char_iter_peekable = &mut self.cursor;
 
-        while let Some(character) = char_iter_peekable.next() {
-            let token = match character {
-                '(' => Some(Token::LeftParen { line }),
-                ')' => Some(Token::RightParen { line }),
-                '{' => Some(Token::RightBrace { line }),
-                '}' => Some(Token::LeftBrace { line }),
-                ',' => Some(Token::Comma { line }),
-                '.' => Some(Token::Dot { line }),
-                '-' => Some(Token::Minus { line }),
-                '+' => Some(Token::Plus { line }),
-                ';' => Some(Token::Semicolon { line }),
-                '*' => Some(Token::Asterisk { line }),
+        loop {
+            // Tokens are located by their first character
+            let start = char_iter_peekable.offset;
+            let (line, column) = (char_iter_peekable.line, char_iter_peekable.column);
+
+            let character = match char_iter_peekable.next() {
+                Some(character) => character,
+                None if self.finished => return None,
+                None => {
+                    self.finished = true;
+                    return Some(Token {
+                        kind: TokenType::Eof,
+                        span: start..start,
+                        line,
+                        column,
+                    });
+                }
+            };
+
+            let kind = match character {
+                '(' => Some(TokenType::LeftParen),
+                ')' => Some(TokenType::RightParen),
+                '{' => Some(TokenType::LeftBrace),
+                '}' => Some(TokenType::RightBrace),
+                '[' => Some(TokenType::LeftBracket),
+                ']' => Some(TokenType::RightBracket),
+                ',' => Some(TokenType::Comma),
+                '.' => Some(TokenType::Dot),
+                '-' => Some(TokenType::Minus),
+                '+' => Some(TokenType::Plus),
+                ';' => Some(TokenType::Semicolon),
+                '*' => Some(TokenType::Asterisk),
+                '%' => Some(TokenType::Percent),
+                '?' => Some(TokenType::Question),
+                ':' => Some(TokenType::Colon),
 
                 // Divide or comment
-                '/' => match char_iter_peekable.next_if_eq(&'/') {
-                    Some(_) => {
+                '/' => {
+                    if char_iter_peekable.next_if_eq(&'/').is_some() {
                         // It's a comment, skip to EOL
                         while char_iter_peekable.next_if(|&c| c != '\n').is_some() {}
                         None
+                    } else if char_iter_peekable.next_if_eq(&'*').is_some() {
+                        Scanner::block_comment_parse(char_iter_peekable, line)
+                    } else {
+                        Some(TokenType::Slash)
                     }
-                    None => Some(Token::Slash { line }),
-                },
+                }
 
                 // Equality and Conditionals
                 '!' => match char_iter_peekable.next_if_eq(&'=') {
-                    Some(_) => Some(Token::BangEqual { line }),
-                    None => Some(Token::Bang { line }),
+                    Some(_) => Some(TokenType::BangEqual),
+                    None => Some(TokenType::Bang),
                 },
                 '=' => match char_iter_peekable.next_if_eq(&'=') {
-                    Some(_) => Some(Token::EqualEqual { line }),
-                    None => Some(Token::Equal { line }),
+                    Some(_) => Some(TokenType::EqualEqual),
+                    None => Some(TokenType::Equal),
                 },
                 '<' => match char_iter_peekable.next_if_eq(&'=') {
-                    Some(_) => Some(Token::LessEqual { line }),
-                    None => Some(Token::Less { line }),
+                    Some(_) => Some(TokenType::LessEqual),
+                    None => Some(TokenType::Less),
                 },
                 '>' => match char_iter_peekable.next_if_eq(&'=') {
-                    Some(_) => Some(Token::GreaterEqual { line }),
-                    None => Some(Token::Greater { line }),
+                    Some(_) => Some(TokenType::GreaterEqual),
+                    None => Some(TokenType::Greater),
                 },
 
-                '"' => {
-                    let mut literal: String = String::new();
+                '"' => Some(Scanner::string_parse(char_iter_peekable, line)),
 
-                    while let Some(&c) = char_iter_peekable.peek() {
-                        if c != '"' {
-                            literal.push(c);
-                        } else {
-                            break;
-                        }
-                        char_iter_peekable.next();
-                    }
+                // Numeric literals
+                '0'..='9' => Some(Scanner::number_parse(char_iter_peekable, start)),
 
-                    char_iter_peekable.next_if_eq(&'"');
+                // Identifiers
+                'a'..='z' | 'A'..='Z' | '_' => {
+                    while char_iter_peekable
+                        .next_if(|&c| c.is_ascii_alphanumeric() || c == '_')
+                        .is_some()
+                    {}
 
-                    Some(Token::String { literal, line })
+                    let text = char_iter_peekable.slice_from(start);
+                    let interner = &mut self.interner;
+                    Some(
+                        Scanner::keyword(text)
+                            .unwrap_or_else(|| TokenType::Identifier(interner.intern(text))),
+                    )
                 }
 
-                // Numeric literals
-                '0'..='9' => {
-                    Scanner::number_parse(&mut char_iter_peekable, line);
-                    None
-                }
-                // Ignore whitespace
-                ' ' | '\r' | '\t' => None,
+                // Ignore whitespace, the cursor takes care of counting lines
+                ' ' | '\r' | '\t' | '\n' => None,
 
-                '\n' => {
-                    line += 1;
-                    None
-                }
+                _ => Some(TokenType::Invalid(format!(
+                    "Unexpected character '{}' on line {}",
+                    character, line
+                ))),
+            };
 
-                _ => Some(Token::Invalid {
-                    message: format!("Unexpected character {} line {}", character, line),
+            if let Some(kind) = kind {
+                return Some(Token {
+                    kind,
+                    span: start..char_iter_peekable.offset,
                     line,
-                }),
-            };
+                    column,
+                });
+            }
+        }
+    }
+}
 
-            if let Some(token) = token {
-                tokens.push(token);
+impl Scanner<'_> {
+    fn keyword(literal: &str) -> Option<TokenType> {
+        match literal {
+            "and" => Some(TokenType::And),
+            "break" => Some(TokenType::Break),
+            "class" => Some(TokenType::Class),
+            "continue" => Some(TokenType::Continue),
+            "else" => Some(TokenType::Else),
+            "false" => Some(TokenType::False),
+            "fun" => Some(TokenType::Fun),
+            "for" => Some(TokenType::For),
+            "if" => Some(TokenType::If),
+            "nil" => Some(TokenType::Nil),
+            "or" => Some(TokenType::Or),
+            "print" => Some(TokenType::Print),
+            "return" => Some(TokenType::Return),
+            "super" => Some(TokenType::Super),
+            "this" => Some(TokenType::This),
+            "true" => Some(TokenType::True),
+            "var" => Some(TokenType::Var),
+            "while" => Some(TokenType::While),
+            _ => None,
+        }
+    }
+
+    /// Skips a block comment, only producing a token if it's never closed. Block comments nest, so
+    /// the comment only ends once every `/*` inside it has a matching `*/`.
+    fn block_comment_parse(char_iter_peekable: &mut Cursor, line: usize) -> Option<TokenType> {
+        let mut depth: usize = 1;
+
+        while let Some(c) = char_iter_peekable.next() {
+            if c == '*' && char_iter_peekable.next_if_eq(&'/').is_some() {
+                depth -= 1;
+                if depth == 0 {
+                    return None;
+                }
+            } else if c == '/' && char_iter_peekable.next_if_eq(&'*').is_some() {
+                depth += 1;
             }
         }
 
-        tokens
+        Some(TokenType::Invalid(format!(
+            "Unterminated block comment starting on line {}",
+            line
+        )))
     }
 
-    fn number_parse(char_iter_peekable: &mut Peekable<Chars>, line: usize) -> Vec<Token> {
-        fn parse_number_chunk(char_iter_peekable: &mut Peekable<Chars>) -> String {
-            let mut literal: String = String::new();
+    fn string_parse(char_iter_peekable: &mut Cursor, line: usize) -> TokenType {
+        let mut literal: String = String::new();
+        let mut error: Option<String> = None;
 
-            while let Some(&c) = char_iter_peekable.peek() {
-                if c.is_numeric() {
-                    literal.push(c);
-                } else {
-                    break;
+        loop {
+            match char_iter_peekable.next() {
+                Some('"') => break,
+                Some('\\') => match char_iter_peekable.next() {
+                    Some('n') => literal.push('\n'),
+                    Some('t') => literal.push('\t'),
+                    Some('r') => literal.push('\r'),
+                    Some('\\') => literal.push('\\'),
+                    Some('"') => literal.push('"'),
+                    Some(c) => {
+                        // Keep scanning to the closing quote so the rest of the string isn't lexed as code
+                        error.get_or_insert(format!(
+                            "Unknown escape sequence '\\{}' in string on line {}",
+                            c, char_iter_peekable.line
+                        ));
+                    }
+                    None => {}
+                },
+                Some(c) => literal.push(c),
+                None => {
+                    return TokenType::Invalid(format!(
+                        "Unterminated string starting on line {}",
+                        line
+                    ))
                 }
-                char_iter_peekable.next();
+            }
+        }
+
+        match error {
+            Some(message) => TokenType::Invalid(message),
+            None => TokenType::String(literal),
+        }
+    }
+
+    fn number_parse(char_iter_peekable: &mut Cursor, start: usize) -> TokenType {
+        /// Consumes a run of digits and `_` separators, returning false if it ended on a separator.
+        /// Every chunk starts on a digit, so this is enough to only allow separators between digits.
+        fn parse_number_chunk(char_iter_peekable: &mut Cursor) -> bool {
+            let mut last = None;
+
+            while let Some(c) = char_iter_peekable.next_if(|&c| c.is_ascii_digit() || c == '_') {
+                last = Some(c);
             }
 
-            literal
+            last != Some('_')
         }
 
-        let mut tokens = vec![];
+        let mut separators_valid = parse_number_chunk(char_iter_peekable);
 
-        let mut literal: String = String::new();
+        // Only treat the dot as a decimal point if a digit follows it, so `5.` scans as `5` `.`
+        if char_iter_peekable.peek() == Some('.')
+            && char_iter_peekable
+                .peek_next()
+                .is_some_and(|c| c.is_ascii_digit())
+        {
+            char_iter_peekable.next();
+            separators_valid &= parse_number_chunk(char_iter_peekable);
+        }
+
+        // Optional exponent, which needs at least one digit after the optional sign
+        if char_iter_peekable
+            .next_if(|&c| c == 'e' || c == 'E')
+            .is_some()
+        {
+            char_iter_peekable.next_if(|&c| c == '+' || c == '-');
 
-        literal.push_str(&parse_number_chunk(char_iter_peekable));
+            if !char_iter_peekable
+                .peek()
+                .is_some_and(|c| c.is_ascii_digit())
+            {
+                return TokenType::Invalid(format!(
+                    "Missing exponent digits in number '{}' on line {}",
+                    char_iter_peekable.slice_from(start),
+                    char_iter_peekable.line
+                ));
+            }
 
-        match char_iter_peekable.peek() {
-            Some('.') => {}
-            _ => tokens.push(Token::Number {
-                literal: literal.parse().unwrap(),
-                line,
-            }),
+            separators_valid &= parse_number_chunk(char_iter_peekable);
         }
 
-        tokens
+        let literal = char_iter_peekable.slice_from(start);
+
+        if !separators_valid {
+            return TokenType::Invalid(format!(
+                "Digit separator '_' must be between digits in number '{}' on line {}",
+                literal, char_iter_peekable.line
+            ));
+        }
+
+        match literal.replace('_', "").parse::<f64>() {
+            Ok(value) if value.is_finite() => TokenType::Number(value),
+            Ok(_) => TokenType::Invalid(format!(
+                "Number '{}' is too large on line {}",
+                literal, char_iter_peekable.line
+            )),
+            Err(err) => TokenType::Invalid(format!(
+                "Invalid number '{}' on line {}: {}",
+                literal, char_iter_peekable.line, err
+            )),
+        }
     }
 }
 
@@ -195,17 +715,49 @@ mod tests {
 
     #[test]
     fn string_and_comment() {
-        let scanner = Scanner::new("\"asd\" // Ignored comment".to_string());
+        let scanner = Scanner::new("\"asd\" // Ignored comment");
 
         let tokens = scanner.scan_tokens();
 
-        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::String("asd".to_string()));
+    }
 
-        assert!(matches!(tokens[0], Token::String { .. }));
+    #[test]
+    fn newline_after_comment() {
+        let scanner = Scanner::new("// Ignored comment\n \"asd\"");
+
+        let tokens = scanner.scan_tokens();
 
-        match &tokens[0] {
-            Token::String { literal, .. } => {
-                assert_eq!(literal, "asd");
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::String("asd".to_string()));
+    }
+
+    #[test]
+    fn braces() {
+        let scanner = Scanner::new("{}");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 3);
+
+        assert_eq!(tokens[0].kind, TokenType::LeftBrace);
+        assert_eq!(tokens[1].kind, TokenType::RightBrace);
+    }
+
+    #[test]
+    fn number_and_comment() {
+        let scanner = Scanner::new("420.69 // Ignored comment");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        match tokens[0].kind {
+            TokenType::Number(literal) => {
+                assert!((literal - 420.69).abs() < f64::EPSILON);
             }
             _ => {
                 unreachable!();
@@ -214,18 +766,138 @@ mod tests {
     }
 
     #[test]
-    fn newline_after_comment() {
-        let scanner = Scanner::new("// Ignored comment\n \"asd\"".to_string());
+    fn empty_source() {
+        let scanner = Scanner::new("");
 
         let tokens = scanner.scan_tokens();
 
         assert_eq!(tokens.len(), 1);
 
-        assert!(matches!(tokens[0], Token::String { .. }));
+        assert_eq!(tokens[0].kind, TokenType::Eof);
+    }
+
+    #[test]
+    fn trailing_eof() {
+        let scanner = Scanner::new("+ -");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.last().unwrap().kind, TokenType::Eof);
+    }
+
+    #[test]
+    fn line_numbers_start_at_one() {
+        let scanner = Scanner::new("(\n)");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens[0].kind, TokenType::LeftParen);
+        assert_eq!(tokens[0].line, 1);
+        assert_eq!(tokens[1].kind, TokenType::RightParen);
+        assert_eq!(tokens[1].line, 2);
+    }
+
+    #[test]
+    fn identifier() {
+        let source = "abc";
+        let scanner = Scanner::new(source);
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::Identifier("abc".into()));
+        assert_eq!(tokens[0].lexeme(source), "abc");
+    }
+
+    #[test]
+    fn identifier_leading_underscore() {
+        let source = "_x1";
+        let scanner = Scanner::new(source);
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::Identifier("_x1".into()));
+        assert_eq!(tokens[0].lexeme(source), "_x1");
+    }
+
+    #[test]
+    fn two_identifiers() {
+        let source = "foo bar";
+        let scanner = Scanner::new(source);
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 3);
+
+        let lexemes: Vec<&str> = tokens
+            .iter()
+            .filter(|tok| matches!(tok.kind, TokenType::Identifier(_)))
+            .map(|tok| tok.lexeme(source))
+            .collect();
+
+        assert_eq!(lexemes, vec!["foo", "bar"]);
+    }
+
+    #[test]
+    fn repeated_identifier_is_interned() {
+        let scanner = Scanner::new("count = count + 1;");
+
+        let tokens = scanner.scan_tokens();
+
+        match (&tokens[0].kind, &tokens[2].kind) {
+            (TokenType::Identifier(first), TokenType::Identifier(second)) => {
+                assert!(Rc::ptr_eq(first, second));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn question_and_colon() {
+        let tokens = Scanner::new("a ? b : c").scan_tokens();
+
+        assert_eq!(tokens[1].kind, TokenType::Question);
+        assert_eq!(tokens[3].kind, TokenType::Colon);
+    }
+
+    #[test]
+    fn keywords() {
+        let scanner = Scanner::new("if else while");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 4);
+
+        assert_eq!(tokens[0].kind, TokenType::If);
+        assert_eq!(tokens[1].kind, TokenType::Else);
+        assert_eq!(tokens[2].kind, TokenType::While);
+    }
+
+    #[test]
+    fn keyword_prefix_is_identifier() {
+        let scanner = Scanner::new("iffy");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::Identifier("iffy".into()));
+    }
+
+    #[test]
+    fn integer() {
+        let scanner = Scanner::new("123");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
 
-        match &tokens[0] {
-            Token::String { literal, .. } => {
-                assert_eq!(literal, "asd");
+        match tokens[0].kind {
+            TokenType::Number(literal) => {
+                assert!((literal - 123.0).abs() < f64::EPSILON);
             }
             _ => {
                 unreachable!();
@@ -234,20 +906,468 @@ mod tests {
     }
 
     #[test]
-    fn number_and_comment() {
-        let scanner = Scanner::new("420.69 // Ignored comment".to_string());
+    #[allow(clippy::approx_constant)]
+    fn decimal() {
+        let scanner = Scanner::new("3.14");
 
         let tokens = scanner.scan_tokens();
 
-        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens.len(), 2);
+
+        match tokens[0].kind {
+            TokenType::Number(literal) => {
+                assert!((literal - 3.14).abs() < f64::EPSILON);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_dot() {
+        let scanner = Scanner::new("5.");
+
+        let tokens = scanner.scan_tokens();
 
-        match &tokens[0] {
-            Token::Number { literal, .. } => {
-                assert!((*literal - 420.69).abs() < f64::EPSILON);
+        assert_eq!(tokens.len(), 3);
+
+        match tokens[0].kind {
+            TokenType::Number(literal) => {
+                assert!((literal - 5.0).abs() < f64::EPSILON);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+        assert_eq!(tokens[1].kind, TokenType::Dot);
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let scanner = Scanner::new("\"abc");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        match &tokens[0].kind {
+            TokenType::Invalid(message) => {
+                assert!(message.starts_with("Unterminated string"));
+                assert_eq!(tokens[0].line, 1);
             }
             _ => {
                 unreachable!();
             }
         }
     }
+
+    #[test]
+    fn string_escapes() {
+        let cases = [
+            ("\"a\\nb\"", "a\nb"),
+            ("\"a\\tb\"", "a\tb"),
+            ("\"a\\rb\"", "a\rb"),
+            ("\"a\\\\b\"", "a\\b"),
+            ("\"a\\\"b\"", "a\"b"),
+        ];
+
+        for (source, expected) in cases.iter() {
+            let scanner = Scanner::new(source);
+
+            let tokens = scanner.scan_tokens();
+
+            assert_eq!(tokens.len(), 2);
+
+            assert_eq!(tokens[0].kind, TokenType::String(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn unknown_escape() {
+        let scanner = Scanner::new("\"a\\qb\"");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert!(matches!(tokens[0].kind, TokenType::Invalid(_)));
+    }
+
+    #[test]
+    fn multi_line_string() {
+        let scanner = Scanner::new("\"a\nb\" (");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 3);
+
+        assert!(matches!(tokens[0].kind, TokenType::String(_)));
+        assert_eq!(tokens[0].line, 1);
+        assert_eq!(tokens[1].kind, TokenType::LeftParen);
+        assert_eq!(tokens[1].line, 2);
+    }
+
+    #[test]
+    fn columns() {
+        let scanner = Scanner::new("var foo\n  bar");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 4);
+
+        let locations: Vec<(usize, usize)> = tokens[..3]
+            .iter()
+            .map(|tok| (tok.line, tok.column))
+            .collect();
+
+        assert_eq!(locations, vec![(1, 1), (1, 5), (2, 3)]);
+    }
+
+    #[test]
+    fn column_of_second_token() {
+        let scanner = Scanner::new("1 <= 2");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens[1].kind, TokenType::LessEqual);
+        assert_eq!(tokens[1].column, 3);
+        assert_eq!(tokens[2].column, 6);
+    }
+
+    #[test]
+    fn block_comment() {
+        let scanner = Scanner::new("/* Ignored comment */ \"asd\"");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert!(matches!(tokens[0].kind, TokenType::String(_)));
+    }
+
+    #[test]
+    fn multi_line_block_comment() {
+        let scanner = Scanner::new("/* Ignored\n comment\n **/ (");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::LeftParen);
+        assert_eq!(tokens[0].line, 3);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let scanner = Scanner::new("( /* Ignored comment *");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 3);
+
+        match &tokens[1].kind {
+            TokenType::Invalid(message) => {
+                assert!(message.starts_with("Unterminated block comment"));
+                assert_eq!(tokens[1].column, 3);
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    #[test]
+    fn nested_block_comment() {
+        let scanner = Scanner::new("/* outer /* inner */ still outer */ \"asd\"");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert!(matches!(tokens[0].kind, TokenType::String(_)));
+    }
+
+    #[test]
+    fn unterminated_nested_block_comment() {
+        let scanner = Scanner::new("/* outer /* inner */ \"asd\"");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert!(matches!(tokens[0].kind, TokenType::Invalid(_)));
+    }
+
+    #[test]
+    fn lexemes_match_source() {
+        let source = "3.14 >= \"a\\tb\" foo";
+        let scanner = Scanner::new(source);
+
+        let tokens = scanner.scan_tokens();
+
+        let lexemes: Vec<&str> = tokens.iter().map(|tok| tok.lexeme(source)).collect();
+
+        assert_eq!(lexemes, vec!["3.14", ">=", "\"a\\tb\"", "foo", ""]);
+        assert_eq!(tokens[2].kind, TokenType::String("a\tb".to_string()));
+    }
+
+    #[test]
+    fn text_without_source() {
+        let tokens = Scanner::new("1_000 >= \"a\\tb\" foo this").scan_tokens();
+
+        let texts: Vec<String> = tokens.iter().map(|tok| tok.text().into_owned()).collect();
+
+        assert_eq!(texts, vec!["1000", ">=", "\"a\\tb\"", "foo", "this", ""]);
+    }
+
+    #[test]
+    fn spans_skip_byte_order_mark() {
+        let source = "\u{feff}print \"é\";";
+
+        let tokens = Scanner::new(source).scan_tokens();
+
+        assert_eq!(tokens[0].span, 3..8);
+        assert_eq!(tokens[0].lexeme(source), "print");
+        assert_eq!(tokens[1].lexeme(source), "\"é\"");
+        assert_eq!(tokens.last().unwrap().span, source.len()..source.len());
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let cases = [("1e10", 1e10), ("6.022e23", 6.022e23), ("2E-3", 2E-3)];
+
+        for (source, expected) in cases.iter() {
+            let scanner = Scanner::new(source);
+
+            let tokens = scanner.scan_tokens();
+
+            assert_eq!(tokens.len(), 2);
+
+            assert_eq!(tokens[0].kind, TokenType::Number(*expected));
+            assert_eq!(tokens[0].lexeme(source), *source);
+        }
+    }
+
+    #[test]
+    fn missing_exponent_digits() {
+        let scanner = Scanner::new("1e");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert!(matches!(tokens[0].kind, TokenType::Invalid(_)));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn digit_separators() {
+        let cases = [("1_000", 1000.0), ("3.141_59", 3.14159)];
+
+        for (source, expected) in cases.iter() {
+            let scanner = Scanner::new(source);
+
+            let tokens = scanner.scan_tokens();
+
+            assert_eq!(tokens.len(), 2);
+
+            assert_eq!(tokens[0].kind, TokenType::Number(*expected));
+            assert_eq!(tokens[0].lexeme(source), *source);
+        }
+    }
+
+    #[test]
+    fn misplaced_digit_separators() {
+        for source in ["5_", "5_.0", "5.0_", "1e5_"].iter() {
+            let scanner = Scanner::new(source);
+
+            let tokens = scanner.scan_tokens();
+
+            assert_eq!(tokens.len(), 2);
+
+            assert!(matches!(tokens[0].kind, TokenType::Invalid(_)));
+        }
+
+        // A leading underscore makes it an identifier rather than a number
+        let scanner = Scanner::new("_5");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens[0].kind, TokenType::Identifier("_5".into()));
+    }
+
+    #[test]
+    fn scan_collects_errors() {
+        let scanner = Scanner::new("1 # 2 @");
+
+        let errors = scanner.scan().unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+
+        assert_eq!(errors[0].line, 1);
+        assert_eq!(errors[0].column, 3);
+        assert_eq!(errors[1].column, 7);
+    }
+
+    #[test]
+    fn scan_without_errors() {
+        let scanner = Scanner::new("1 + 2");
+
+        let tokens = scanner.scan().unwrap();
+
+        assert_eq!(tokens.len(), 4);
+    }
+
+    #[test]
+    fn tokens_arrive_one_at_a_time() {
+        let source = "var x";
+        let mut scanner = Scanner::new(source);
+
+        assert_eq!(scanner.next().map(|tok| tok.kind), Some(TokenType::Var));
+
+        let identifier = scanner.next().unwrap();
+        assert_eq!(identifier.kind, TokenType::Identifier("x".into()));
+        assert_eq!(identifier.lexeme(source), "x");
+
+        assert_eq!(scanner.next().map(|tok| tok.kind), Some(TokenType::Eof));
+        assert_eq!(scanner.next(), None);
+    }
+
+    #[test]
+    fn display() {
+        let scanner = Scanner::new("3.14 foo \"bar\" <=");
+
+        let lines: Vec<String> = scanner.map(|tok| tok.to_string()).collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "NUMBER 3.14",
+                "IDENTIFIER foo",
+                "STRING \"bar\"",
+                "LESS_EQUAL <=",
+                "EOF"
+            ]
+        );
+    }
+
+    #[test]
+    fn display_with_source() {
+        let source = "1_000 \"a\\tb\"";
+        let scanner = Scanner::new(source);
+
+        let lines: Vec<String> = scanner
+            .map(|tok| tok.with_source(source).to_string())
+            .collect();
+
+        assert_eq!(lines, vec!["NUMBER 1_000", "STRING \"a\\tb\"", "EOF"]);
+    }
+
+    #[test]
+    fn modulo() {
+        let scanner = Scanner::new("10 % 3");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 4);
+
+        assert_eq!(tokens[0].kind, TokenType::Number(10.0));
+        assert_eq!(tokens[1].kind, TokenType::Percent);
+        assert_eq!(tokens[2].kind, TokenType::Number(3.0));
+    }
+
+    #[test]
+    fn brackets() {
+        let scanner = Scanner::new("[1, 2]");
+
+        let kinds: Vec<TokenType> = scanner.map(|tok| tok.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenType::LeftBracket,
+                TokenType::Number(1.0),
+                TokenType::Comma,
+                TokenType::Number(2.0),
+                TokenType::RightBracket,
+                TokenType::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn shebang() {
+        let scanner = Scanner::new("#!/usr/bin/env rlox\nprint");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 2);
+
+        assert_eq!(tokens[0].kind, TokenType::Print);
+        assert_eq!(tokens[0].line, 2);
+    }
+
+    #[test]
+    fn hash_mid_file() {
+        let scanner = Scanner::new("print #!/usr/bin/env rlox");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens[0].kind, TokenType::Print);
+        assert!(matches!(tokens[1].kind, TokenType::Invalid(_)));
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        // Spans are byte offsets so they still count the mark, lines and columns don't
+        let located = |source| {
+            Scanner::new(source)
+                .map(|tok| (tok.kind, tok.line, tok.column))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(located("\u{feff}var x"), located("var x"));
+    }
+
+    #[test]
+    fn number_too_large() {
+        let scanner = Scanner::new("1e400 1");
+
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(tokens.len(), 3);
+
+        assert!(matches!(tokens[0].kind, TokenType::Invalid(_)));
+        assert_eq!(tokens[1].kind, TokenType::Number(1.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tokens_to_json() {
+        let tokens = Scanner::new("var a = \"b\";\nprint 1;").scan_tokens();
+        let json = serde_json::to_string(&tokens).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let tokens = parsed.as_array().unwrap();
+        assert_eq!(tokens.len(), 9);
+        assert_eq!(
+            tokens[0],
+            serde_json::json!({"kind": {"type": "Var"}, "lexeme": "var", "line": 1, "column": 1})
+        );
+        assert_eq!(
+            tokens[1]["kind"],
+            serde_json::json!({"type": "Identifier", "value": "a"})
+        );
+        assert_eq!(
+            tokens[3]["kind"],
+            serde_json::json!({"type": "String", "value": "b"})
+        );
+        assert_eq!(
+            tokens[6]["kind"],
+            serde_json::json!({"type": "Number", "value": 1.0})
+        );
+        assert_eq!(tokens[6]["line"], 2);
+        assert_eq!(tokens[8]["kind"], serde_json::json!({"type": "Eof"}));
+    }
 }
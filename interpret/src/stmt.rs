@@ -0,0 +1,84 @@
+use std::rc::Rc;
+
+use crate::expr::Expr;
+use crate::scanner::Token;
+
+/// Serializes in the same shape as `Expr`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+    },
+    Class {
+        name: Token,
+        /// Always `Expr::Variable`
+        superclass: Option<Expr>,
+        /// Always `Stmt::Function`
+        methods: Vec<Stmt>,
+    },
+    Function {
+        name: Token,
+        /// Shared with every `LoxFunction` made from the declaration
+        params: Rc<[Token]>,
+        body: Rc<Vec<Stmt>>,
+    },
+    Return {
+        /// Kept to locate `return` outside of a function
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    /// Keyword kept to locate uses outside of a loop, as for `Continue`
+    Break {
+        keyword: Token,
+    },
+    Continue {
+        keyword: Token,
+    },
+    Block(Vec<Stmt>),
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        /// Increment of a desugared `for`, run after the body even when `continue` cuts it short
+        increment: Option<Expr>,
+    },
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use serde_json::json;
+
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn statements_to_json() {
+        let statements = Parser::new(Scanner::new("var a = 1; print a + 2;").scan_tokens())
+            .parse()
+            .unwrap();
+        let json = serde_json::to_value(&statements).unwrap();
+
+        assert_eq!(json[0]["type"], "Var");
+        assert_eq!(json[0]["value"]["name"]["lexeme"], "a");
+        assert_eq!(
+            json[0]["value"]["initializer"],
+            json!({"type": "Literal", "value": {"type": "Number", "value": 1.0}})
+        );
+
+        let sum = &json[1]["value"];
+        assert_eq!(json[1]["type"], "Print");
+        assert_eq!(sum["type"], "Binary");
+        assert_eq!(sum["value"]["op"]["lexeme"], "+");
+        assert_eq!(sum["value"]["left"]["type"], "Variable");
+        assert!(sum["value"]["left"]["value"].get("depth").is_none());
+    }
+}
@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance};
+
+#[derive(Clone)]
+pub enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    /// Shared, so assigning a list aliases it rather than copying
+    List(Rc<RefCell<Vec<Value>>>),
+}
+
+impl Value {
+    /// Name of the value's type, for error messages and `type()`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Callable(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, &mut Vec::new())
+    }
+}
+
+impl Value {
+    /// Display for a value nested in the lists of `printing`, which are still being printed. A list
+    /// that contains itself is printed as `[...]` where it comes up again
+    fn write(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        printing: &mut Vec<*const RefCell<Vec<Value>>>,
+    ) -> fmt::Result {
+        match self {
+            // Integral numbers are printed without a trailing `.0`, like Lox does
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Str(value) => f.write_str(value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Nil => f.write_str("nil"),
+            Value::Callable(callable) => write!(f, "{}", callable),
+            Value::Class(class) => write!(f, "{}", class),
+            Value::Instance(instance) => write!(f, "{}", instance.borrow()),
+            Value::List(list) if printing.contains(&Rc::as_ptr(list)) => f.write_str("[...]"),
+            Value::List(list) => {
+                printing.push(Rc::as_ptr(list));
+                f.write_str("[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    element.write(f, printing)?;
+                }
+                printing.pop();
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(value) => f.debug_tuple("Number").field(value).finish(),
+            Value::Str(value) => f.debug_tuple("Str").field(value).finish(),
+            Value::Bool(value) => f.debug_tuple("Bool").field(value).finish(),
+            Value::Nil => f.write_str("Nil"),
+            Value::Callable(callable) => write!(f, "Callable({})", callable),
+            Value::Class(class) => write!(f, "Class({})", class),
+            Value::Instance(instance) => write!(f, "Instance({})", instance.borrow()),
+            Value::List(_) => write!(f, "List({})", self),
+        }
+    }
+}
+
+/// Callables, classes, instances and lists are only equal to themselves
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::Str(left), Value::Str(right)) => left == right,
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Callable(left), Value::Callable(right)) => Rc::ptr_eq(left, right),
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => Rc::ptr_eq(left, right),
+            (Value::List(left), Value::List(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::*;
+
+    #[test]
+    fn display_integral_number() {
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+    }
+
+    #[test]
+    fn display_fractional_number() {
+        assert_eq!(Value::Number(3.5).to_string(), "3.5");
+    }
+
+    #[test]
+    fn display_others() {
+        assert_eq!(Value::Str("foo".to_string()).to_string(), "foo");
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Nil.to_string(), "nil");
+    }
+
+    #[test]
+    fn display_self_referencing_list() {
+        let list = Rc::new(RefCell::new(vec![Value::Number(1.0)]));
+        let value = Value::List(list.clone());
+        list.borrow_mut().push(value.clone());
+
+        assert_eq!(value.to_string(), "[1, [...]]");
+        assert_eq!(format!("{:?}", value), "List([1, [...]])");
+
+        // The same list twice side by side isn't a cycle
+        let inner = Value::List(Rc::new(RefCell::new(vec![Value::Nil])));
+        let outer = Value::List(Rc::new(RefCell::new(vec![inner.clone(), inner])));
+        assert_eq!(outer.to_string(), "[[nil], [nil]]");
+    }
+}
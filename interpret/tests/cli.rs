@@ -0,0 +1,159 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Writes `source` to a script named `name` and runs the interpreter binary on it, after `flags`
+fn run_with(flags: &[&str], name: &str, source: &str) -> Output {
+    let path: PathBuf = std::env::temp_dir().join(format!("rlox-{}-{}", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_interpret"))
+        .args(flags)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+fn run_script(name: &str, source: &str) -> Output {
+    run_with(&[], name, source)
+}
+
+#[test]
+fn successful_script() {
+    let output = run_script("ok.lox", "print 1 + 2;");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"3\n");
+}
+
+#[test]
+fn syntax_error_exit_code() {
+    let output = run_script("syntax.lox", "print 1 +;");
+
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn runtime_error_exit_code() {
+    let output = run_script("runtime.lox", "print 1;\nprint -nil;\nprint 2;");
+
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(output.stdout, b"1\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Operand must be a number"));
+}
+
+#[test]
+fn piped_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_interpret"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"var a = \"pi\";\nprint a + \"ped\";\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"piped\n");
+}
+
+#[test]
+fn script_reads_input() {
+    let path: PathBuf = std::env::temp_dir().join(format!("rlox-{}-input.lox", std::process::id()));
+    fs::write(
+        &path,
+        "var name = input();\nprint \"hi \" + name;\nprint input();\n",
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_interpret"))
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"lox\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"hi lox\nnil\n");
+}
+
+#[test]
+fn tokens_flag() {
+    let output = run_with(&["--tokens"], "tokens.lox", "var a = 1;\nprint a;");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "VAR var\nIDENTIFIER a\nEQUAL =\nNUMBER 1\nSEMICOLON ;\nPRINT print\nIDENTIFIER a\nSEMICOLON ;\nEOF\n"
+    );
+}
+
+#[test]
+fn ast_flag() {
+    let output = run_with(&["--ast"], "ast.lox", "print 1 + 2;\nvar a = -b;");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, b"(print (+ 1 2))\n(var a = (- b))\n");
+}
+
+#[test]
+fn ast_flag_parse_error() {
+    let output = run_with(&["--ast"], "ast-error.lox", "print 1;\nprint (2;");
+
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--> 2:9"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn tokens_json_flag() {
+    let output = run_with(&["--tokens-json"], "tokens.json.lox", "nil;");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"kind":{"type":"Nil"},"lexeme":"nil","line":1,"column":1},"#,
+            r#"{"kind":{"type":"Semicolon"},"lexeme":";","line":1,"column":4},"#,
+            r#"{"kind":{"type":"Eof"},"lexeme":"","line":1,"column":5}]"#,
+            "\n"
+        )
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn ast_json_flag() {
+    let output = run_with(&["--ast-json"], "ast.json.lox", "print nil;");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        r#"[{"type":"Print","value":{"type":"Literal","value":{"type":"Nil"}}}]"#.to_string()
+            + "\n"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn usage_lists_json_flags() {
+    let output = run_with(&["--bogus"], "usage.lox", "");
+
+    assert_eq!(output.status.code(), Some(64));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Usage: rlox [--tokens | --tokens-json | --ast | --ast-json] [script]\n"
+    );
+}
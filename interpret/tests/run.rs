@@ -0,0 +1,130 @@
+use std::io::{self, Write};
+
+use interpret::LoxError;
+
+#[test]
+fn prints_output() {
+    let mut out = Vec::new();
+
+    interpret::run("fun twice(x) { return x * 2; } print twice(21);", &mut out).unwrap();
+
+    assert_eq!(out, b"42\n");
+}
+
+#[test]
+fn output_before_runtime_error() {
+    let mut out = Vec::new();
+
+    let errors = interpret::run("print 1;\nprint nil + 1;", &mut out).unwrap_err();
+
+    assert_eq!(out, b"1\n");
+    assert!(matches!(&errors[..], [LoxError::Runtime(err)] if err.line == 2));
+}
+
+/// Writer that takes that many more bytes and then fails, like a pipe closed by its reader
+struct ClosesAfter(usize);
+
+impl Write for ClosesAfter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+        }
+        self.0 -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_failure_is_io_error() {
+    let errors = interpret::run("print 1;", &mut ClosesAfter(0)).unwrap_err();
+
+    assert!(matches!(
+        &errors[..],
+        [LoxError::Io(err)] if err.kind == io::ErrorKind::BrokenPipe
+    ));
+}
+
+#[test]
+fn output_streams_while_running() {
+    // Never finishes, so this only returns if each print reaches `out` as it happens
+    let errors = interpret::run("while (true) print 1;", &mut ClosesAfter(1000)).unwrap_err();
+
+    assert!(matches!(&errors[..], [LoxError::Io(_)]));
+}
+
+#[test]
+fn parse_errors_run_nothing() {
+    let mut out = Vec::new();
+
+    let errors = interpret::run("print 1;\nprint (;\nvar;", &mut out).unwrap_err();
+
+    assert!(out.is_empty());
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|err| matches!(err, LoxError::Parse(_))));
+}
+
+#[test]
+fn interpret_source_output() {
+    let output = interpret::interpret_source(
+        "class Greeter { greet(name) { print \"Hi \" + name; } }\n\
+         var g = Greeter();\n\
+         g.greet(\"Ann\");\n\
+         for (var i = 0; i < 2; i = i + 1) print i;",
+    );
+
+    assert_eq!(
+        output,
+        Ok(vec!["Hi Ann".to_string(), "0".to_string(), "1".to_string()])
+    );
+}
+
+#[test]
+fn interpret_source_collects_errors() {
+    let errors = interpret::interpret_source("var a = @;\nprint (1;").unwrap_err();
+
+    let locations: Vec<(&str, usize)> = errors
+        .iter()
+        .map(|err| match err {
+            LoxError::Scan(err) => ("scan", err.line),
+            LoxError::Parse(err) => ("parse", err.line),
+            LoxError::Resolve(err) => ("resolve", err.line),
+            LoxError::Runtime(err) => ("runtime", err.line),
+            LoxError::Io(_) => ("io", 0),
+        })
+        .collect();
+    assert_eq!(locations, [("scan", 1), ("parse", 1), ("parse", 2)]);
+
+    match &errors[0] {
+        LoxError::Scan(err) => assert_eq!(err.column, 9),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn interpret_source_runtime_error() {
+    let errors = interpret::interpret_source("print 1;\nprint \"a\" - 1;").unwrap_err();
+
+    match &errors[..] {
+        [LoxError::Runtime(err)] => {
+            assert_eq!(err.line, 2);
+            assert_eq!(err.column, 11);
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn deep_recursion_hits_depth_limit() {
+    // Called from the test thread's default stack, which is far smaller than the interpreter needs
+    let source = "fun f(n) {\n  return f(n + 1);\n}\nf(0);";
+
+    let errors = interpret::interpret_source(source).unwrap_err();
+    assert!(matches!(&errors[..], [LoxError::Runtime(err)] if err.message == "Stack overflow"));
+
+    let errors = interpret::run(source, &mut Vec::new()).unwrap_err();
+    assert!(matches!(&errors[..], [LoxError::Runtime(err)] if err.line == 2));
+}